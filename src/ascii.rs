@@ -0,0 +1,175 @@
+// Plain text snapshots of levels
+//   Each tile is written as its glyph from the prefab legend, with the
+//   player and creatures drawn over the tile they stand on. Text written by
+//   `to_ascii` reads back with `from_ascii`, so generation bugs can be kept
+//   as snapshots and small maps can be typed out by hand. Only layout
+//   survives the trip: deposits come back full, what the player has seen or
+//   dug at is dropped, tiles under the player and creatures read back as
+//   open floor, and hazards aren't written at all.
+
+use crate::creature::Creature;
+use crate::game::Tile;
+use crate::game::TileMap;
+use crate::grid::Grid;
+use crate::grid::Pos;
+use crate::mapgen;
+use crate::prefab;
+use crate::prefab::PrefabCell;
+
+// Where snapshots of the current level are saved during play
+pub const SNAPSHOT_DIR: &str = "snapshots";
+
+// Glyph used for anything missing from the legend, so a dump never fails
+const UNKNOWN_GLYPH: char = '?';
+
+pub struct AsciiMap {
+    pub tilemap: TileMap,
+    pub player: Option<Pos>,
+    pub creatures: Vec<Creature>,
+}
+
+pub fn to_ascii(tilemap: &TileMap, player: Option<Pos>, creatures: &[Creature]) -> String {
+    let mut glyphs = Grid::from_fn(tilemap.width(), tilemap.height(), |pos| {
+        tilemap
+            .get(pos)
+            .and_then(|tile| prefab::glyph(prefab::tile_name(tile)))
+            .unwrap_or(UNKNOWN_GLYPH)
+    });
+    for creature in creatures {
+        let glyph = prefab::glyph(prefab::creature_name(creature.kind)).unwrap_or(UNKNOWN_GLYPH);
+        glyphs.set(creature.pos, glyph);
+    }
+    if let Some(player) = player {
+        glyphs.set(player, PrefabCell::Spawn.glyph());
+    }
+
+    let mut text = String::new();
+    for row in glyphs.rows() {
+        text.extend(row);
+        text.push('\n');
+    }
+    text
+}
+
+// Creatures and the player are assumed to be standing on open floor
+pub fn from_ascii(text: &str) -> Result<AsciiMap, String> {
+    let rows: Vec<Vec<char>> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.trim().chars().collect())
+        .collect();
+    let height = rows.len();
+    let width = rows.first().map_or(0, |row| row.len());
+    if width == 0 {
+        return Err("Map is empty".to_string());
+    }
+    if let Some(y) = rows.iter().position(|row| row.len() != width) {
+        return Err(format!(
+            "Row {} is {} wide, expected {}",
+            y,
+            rows[y].len(),
+            width
+        ));
+    }
+
+    let mut cells = Grid::new(width, height, PrefabCell::Tile(Tile::Empty));
+    for (y, row) in rows.iter().enumerate() {
+        for (x, glyph) in row.iter().enumerate() {
+            let cell = prefab::DEFAULT_LEGEND
+                .iter()
+                .find(|(legend_glyph, _)| legend_glyph == glyph)
+                .and_then(|(_, name)| PrefabCell::from_name(name))
+                .ok_or_else(|| format!("Unknown character `{}` at ({}, {})", glyph, x, y))?;
+            cells.set(Pos::new(x as i32, y as i32), cell);
+        }
+    }
+
+    let mut player = None;
+    let mut creatures = vec![];
+    let tiles = Grid::from_fn(width, height, |pos| match cells.get(pos) {
+        Some(PrefabCell::Tile(tile)) => tile.clone(),
+        Some(PrefabCell::Deposit(key)) => mapgen::preview_deposit(key).unwrap_or(Tile::Empty),
+        Some(PrefabCell::Creature(kind)) => {
+            creatures.push(Creature::new(*kind, pos));
+            Tile::Empty
+        }
+        Some(PrefabCell::Spawn) => {
+            player = Some(pos);
+            Tile::Empty
+        }
+        None => Tile::Empty,
+    });
+
+    Ok(AsciiMap {
+        tilemap: TileMap::from_tiles(tiles),
+        player,
+        creatures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creature::CreatureKind;
+
+    const MAP: &str = "\
+#######
+#.@.c.#
+#~,&i>#
+#h.^+E#
+#######
+";
+
+    #[test]
+    fn round_trip() {
+        let map = from_ascii(MAP).unwrap();
+        assert_eq!(to_ascii(&map.tilemap, map.player, &map.creatures), MAP);
+    }
+
+    #[test]
+    fn reads_player_and_creatures() {
+        let map = from_ascii(MAP).unwrap();
+        assert_eq!(map.tilemap.width(), 7);
+        assert_eq!(map.tilemap.height(), 5);
+        assert_eq!(map.player, Some(Pos::new(2, 1)));
+
+        let creatures: Vec<_> = map.creatures.iter().map(|c| (c.kind, c.pos)).collect();
+        assert_eq!(
+            creatures,
+            vec![
+                (CreatureKind::CaveCrawler, Pos::new(4, 1)),
+                (CreatureKind::RockHound, Pos::new(1, 3)),
+            ]
+        );
+        // The player and creatures stand on open floor
+        for pos in [Pos::new(2, 1), Pos::new(4, 1), Pos::new(1, 3)] {
+            assert!(matches!(map.tilemap.get(pos), Some(Tile::Empty)));
+        }
+        assert!(matches!(
+            map.tilemap.get(Pos::new(4, 2)),
+            Some(Tile::Resource(resource)) if resource.key == "iron_ore"
+        ));
+        assert!(matches!(
+            map.tilemap.get(Pos::new(3, 2)),
+            Some(Tile::Cache(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_ragged_rows() {
+        let err = from_ascii("###\n##\n###\n").err().unwrap();
+        assert_eq!(err, "Row 1 is 2 wide, expected 3");
+    }
+
+    #[test]
+    fn rejects_empty_map() {
+        assert!(from_ascii("").is_err());
+        assert!(from_ascii("\n   \n").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_glyphs() {
+        let err = from_ascii("#Z#\n").err().unwrap();
+        assert_eq!(err, "Unknown character `Z` at (1, 0)");
+    }
+}
@@ -0,0 +1,187 @@
+use macroquad::prelude::*;
+
+use crate::game::TileMap;
+use crate::game::Visibility;
+use crate::grid::Pos;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CreatureKind {
+    CaveCrawler,
+    RockHound,
+}
+
+pub struct CreatureStats {
+    pub name: &'static str,
+    pub color: Color,
+    pub max_hp: f32,
+    pub attack: f32,
+    pub defense: f32,
+    // How far, in tiles, the creature notices the player from
+    pub sight: u32,
+    // Action points gained per tick, against the player's `turn::NORMAL_SPEED`
+    pub speed: i32,
+}
+
+impl CreatureKind {
+    pub fn stats(&self) -> CreatureStats {
+        match self {
+            CreatureKind::CaveCrawler => CreatureStats {
+                name: "cave crawler",
+                color: ORANGE,
+                max_hp: 5.0,
+                attack: 2.0,
+                defense: 0.0,
+                sight: 5,
+                speed: 8,
+            },
+            CreatureKind::RockHound => CreatureStats {
+                name: "rock hound",
+                color: MAROON,
+                max_hp: 10.0,
+                attack: 4.0,
+                defense: 1.0,
+                sight: 8,
+                speed: 14,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Behavior {
+    Wander,
+    Chase,
+    Flee,
+}
+
+// Below this fraction of max hp, creatures that see the player run for it
+const FLEE_FRACTION: f32 = 0.3;
+
+// What a creature did with its turn, for the game state to act on
+pub struct CreatureTurn {
+    pub message: Option<String>,
+    pub attacked: bool,
+}
+
+pub struct Creature {
+    pub kind: CreatureKind,
+    pub pos: Pos,
+    pub hp: f32,
+    pub behavior: Behavior,
+    // Banked toward the next turn; the creature acts while this isn't negative
+    pub action_points: i32,
+}
+
+impl Creature {
+    pub fn new(kind: CreatureKind, pos: Pos) -> Self {
+        Creature {
+            kind,
+            pos,
+            hp: kind.stats().max_hp,
+            behavior: Behavior::Wander,
+            action_points: 0,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.kind.stats().name
+    }
+
+    // Sight is treated as symmetric: a creature sees the player when it is
+    //   standing in the player's field of view and close enough to notice
+    pub fn can_see(&self, tilemap: &TileMap, player: Pos) -> bool {
+        tilemap.visibility(self.pos) == Visibility::Visible
+            && self.pos.distance_sq(player) <= (self.kind.stats().sight as i32).pow(2)
+    }
+
+    // Pick this turn's behavior, returning a message if it changed in a way
+    //   the player should hear about
+    fn update_behavior(&mut self, tilemap: &TileMap, player: Pos) -> Option<String> {
+        let stats = self.kind.stats();
+        let next = if !self.can_see(tilemap, player) {
+            Behavior::Wander
+        } else if self.hp < FLEE_FRACTION * stats.max_hp {
+            Behavior::Flee
+        } else {
+            Behavior::Chase
+        };
+
+        let previous = self.behavior;
+        self.behavior = next;
+        match (previous, next) {
+            (Behavior::Wander, Behavior::Chase) => Some(format!("A {} spots you!", stats.name)),
+            (_, Behavior::Flee) if previous != Behavior::Flee => {
+                Some(format!("The {} turns to flee", stats.name))
+            }
+            _ => None,
+        }
+    }
+
+    // Take one turn. `is_blocked` reports tiles the creature may not step
+    //   onto, such as walls, the player or other creatures.
+    pub fn take_turn<B>(&mut self, tilemap: &TileMap, player: Pos, is_blocked: B) -> CreatureTurn
+    where
+        B: Fn(Pos) -> bool,
+    {
+        let message = self.update_behavior(tilemap, player);
+
+        // Chasing creatures next to the player go for them instead of moving
+        if self.behavior == Behavior::Chase && self.pos.distance_sq(player) == 1 {
+            return CreatureTurn {
+                message,
+                attacked: true,
+            };
+        }
+
+        let mut options: Vec<Pos> = tilemap
+            .tiles
+            .neighbors(self.pos)
+            .filter(|next| {
+                let safe = matches!(
+                    tilemap.get(*next),
+                    Some(tile) if tile.props().passable && tile.props().hazard_damage <= 0.0
+                );
+                safe && !is_blocked(*next)
+            })
+            .collect();
+
+        let here = self.pos.distance_sq(player);
+        let step = match self.behavior {
+            Behavior::Wander => {
+                // Wandering creatures dawdle about half the time
+                if options.is_empty() || rand::gen_range(0, 2) == 0 {
+                    None
+                } else {
+                    Some(options[rand::gen_range(0, options.len())])
+                }
+            }
+            Behavior::Chase => {
+                options.retain(|pos| pos.distance_sq(player) < here);
+                options
+                    .into_iter()
+                    .min_by_key(|pos| pos.distance_sq(player))
+            }
+            Behavior::Flee => {
+                options.retain(|pos| pos.distance_sq(player) > here);
+                options
+                    .into_iter()
+                    .max_by_key(|pos| pos.distance_sq(player))
+            }
+        };
+
+        if let Some(next) = step {
+            self.pos = next;
+        }
+        CreatureTurn {
+            message,
+            attacked: false,
+        }
+    }
+}
+
+// Damage dealt by one blow, with a little variance so fights aren't foregone
+pub fn roll_damage(attack: f32, defense: f32) -> f32 {
+    (attack * rand::gen_range(0.75, 1.25) - defense)
+        .max(1.0)
+        .round()
+}
@@ -0,0 +1,236 @@
+// Developer screen for painting prefabs and fixed maps
+//   The editor works directly on a `Prefab`, and keeps a `TileMap` and
+//   creature list mirroring it so the embark renderer can draw it. Cells
+//   that haven't been painted leave generated terrain alone when the
+//   prefab is stamped, and show up as fog.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::creature::Creature;
+use crate::game::Biome;
+use crate::game::Tile;
+use crate::game::TileMap;
+use crate::game::Visibility;
+use crate::grid::Grid;
+use crate::grid::Pos;
+use crate::mapgen;
+use crate::prefab;
+use crate::prefab::Prefab;
+use crate::prefab::PrefabCell;
+
+// Fixed maps are saved here, apart from the prefabs the generator picks up.
+//   Each one can be played as a site of its own from the base.
+pub const MAP_DIR: &str = "maps";
+
+// Size of a freshly started prefab, and the limits on resizing it
+const NEW_WIDTH: usize = 24;
+const NEW_HEIGHT: usize = 16;
+const MAX_SIZE: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SaveTarget {
+    Prefab,
+    Map,
+}
+
+impl SaveTarget {
+    pub fn dir(&self) -> &'static str {
+        match self {
+            SaveTarget::Prefab => prefab::PREFAB_DIR,
+            SaveTarget::Map => MAP_DIR,
+        }
+    }
+}
+
+pub struct EditorState {
+    pub prefab: Prefab,
+    // Where the prefab was loaded from or last saved to
+    pub path: Option<PathBuf>,
+    // Name of the cell to paint with, or `None` to erase
+    pub brush: Option<&'static str>,
+    // Tile the camera is centered on
+    pub focus: Pos,
+    pub tilemap: TileMap,
+    pub creatures: Vec<Creature>,
+    pub spawn: Option<Pos>,
+    // Files available to load, refreshed whenever something is saved
+    pub saved_files: Vec<PathBuf>,
+}
+
+impl Default for EditorState {
+    fn default() -> Self {
+        let prefab = Prefab {
+            name: "Untitled".to_string(),
+            frequency: Biome::ALL.into_iter().map(|biome| (biome, 1)).collect(),
+            cells: Grid::new(NEW_WIDTH, NEW_HEIGHT, None),
+        };
+        EditorState::new(prefab, None)
+    }
+}
+
+impl EditorState {
+    pub fn new(prefab: Prefab, path: Option<PathBuf>) -> Self {
+        let mut editor = EditorState {
+            focus: Pos::new(
+                prefab.cells.width() as i32 / 2,
+                prefab.cells.height() as i32 / 2,
+            ),
+            prefab,
+            path,
+            brush: Some("wall"),
+            tilemap: TileMap::new(0, 0, Tile::Empty),
+            creatures: vec![],
+            spawn: None,
+            saved_files: vec![],
+        };
+        editor.rebuild();
+        editor.refresh_saved_files();
+        editor
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let prefab = prefab::load_file(path)?;
+        Ok(EditorState::new(prefab, Some(path.to_path_buf())))
+    }
+
+    // Mirror every cell of the prefab into the tilemap and creature list
+    fn rebuild(&mut self) {
+        let cells = &self.prefab.cells;
+        self.tilemap = TileMap::from_tiles(Grid::from_fn(cells.width(), cells.height(), |pos| {
+            cells
+                .get(pos)
+                .and_then(Option::as_ref)
+                .map_or(Tile::Empty, preview_tile)
+        }));
+        self.creatures.clear();
+        self.spawn = None;
+        for (pos, cell) in self.prefab.cells.iter() {
+            let visibility = match cell {
+                Some(_) => Visibility::Visible,
+                None => Visibility::Unseen,
+            };
+            self.tilemap.visibility.set(pos, visibility);
+            match cell {
+                Some(PrefabCell::Creature(kind)) => self.creatures.push(Creature::new(*kind, pos)),
+                Some(PrefabCell::Spawn) => self.spawn = Some(pos),
+                _ => {}
+            }
+        }
+    }
+
+    // Paint the current brush onto `pos`. There's only ever one spawn, so
+    //   painting a new one clears the old.
+    pub fn paint(&mut self, pos: Pos) {
+        let cell = self.brush.and_then(PrefabCell::from_name);
+        if !self.prefab.cells.in_bounds(pos) {
+            return;
+        }
+        if matches!(cell, Some(PrefabCell::Spawn)) {
+            if let Some(old) = self.spawn.filter(|old| *old != pos) {
+                self.set_cell(old, Some(PrefabCell::Tile(Tile::Empty)));
+            }
+            self.spawn = Some(pos);
+        } else if self.spawn == Some(pos) {
+            self.spawn = None;
+        }
+        self.set_cell(pos, cell);
+    }
+
+    fn set_cell(&mut self, pos: Pos, cell: Option<PrefabCell>) {
+        let tile = cell.as_ref().map_or(Tile::Empty, preview_tile);
+        let visibility = match cell {
+            Some(_) => Visibility::Visible,
+            None => Visibility::Unseen,
+        };
+        self.creatures.retain(|creature| creature.pos != pos);
+        if let Some(PrefabCell::Creature(kind)) = cell {
+            self.creatures.push(Creature::new(kind, pos));
+        }
+        self.tilemap.set(pos, tile);
+        self.tilemap.visibility.set(pos, visibility);
+        self.prefab.cells.set(pos, cell);
+    }
+
+    // Grow or shrink the prefab from its bottom right corner
+    pub fn resize(&mut self, delta: Pos) {
+        let width = (self.prefab.cells.width() as i32 + delta.x).clamp(1, MAX_SIZE as i32);
+        let height = (self.prefab.cells.height() as i32 + delta.y).clamp(1, MAX_SIZE as i32);
+        let old = &self.prefab.cells;
+        self.prefab.cells = Grid::from_fn(width as usize, height as usize, |pos| {
+            old.get(pos).cloned().flatten()
+        });
+        self.rebuild();
+        self.pan(Pos::new(0, 0));
+    }
+
+    // Move the camera, keeping it over the prefab
+    pub fn pan(&mut self, step: Pos) {
+        let focus = self.focus + step;
+        self.focus = Pos::new(
+            focus.x.clamp(0, self.prefab.cells.width() as i32 - 1),
+            focus.y.clamp(0, self.prefab.cells.height() as i32 - 1),
+        );
+    }
+
+    pub fn adjust_frequency(&mut self, biome: Biome, delta: i32) {
+        let frequency = self.prefab.frequency(biome).saturating_add_signed(delta);
+        self.prefab.frequency.insert(biome, frequency);
+    }
+
+    // Write the prefab out, back to the file it came from if that's in the
+    //   right place, otherwise to a new file named after it
+    pub fn save(&mut self, target: SaveTarget) -> Result<PathBuf, String> {
+        let dir = Path::new(target.dir());
+        let path = match &self.path {
+            Some(path) if path.parent() == Some(dir) => path.clone(),
+            _ => unused_path(dir, &self.prefab.name),
+        };
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        fs::write(&path, self.prefab.to_text()).map_err(|err| err.to_string())?;
+        self.path = Some(path.clone());
+        self.refresh_saved_files();
+        Ok(path)
+    }
+
+    pub fn refresh_saved_files(&mut self) {
+        let mut files = vec![];
+        for dir in [prefab::PREFAB_DIR, MAP_DIR] {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            files.extend(
+                entries
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "txt")),
+            );
+        }
+        files.sort();
+        self.saved_files = files;
+    }
+}
+
+// How a painted cell looks in the editor
+fn preview_tile(cell: &PrefabCell) -> Tile {
+    match cell {
+        PrefabCell::Tile(tile) => tile.clone(),
+        PrefabCell::Deposit(key) => mapgen::preview_deposit(key).unwrap_or(Tile::Empty),
+        PrefabCell::Creature(_) | PrefabCell::Spawn => Tile::Empty,
+    }
+}
+
+// A file in `dir` named after `name` that doesn't exist yet
+fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let stem: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let mut path = dir.join(format!("{}.txt", stem));
+    let mut n = 1;
+    while path.exists() {
+        n += 1;
+        path = dir.join(format!("{}_{}.txt", stem, n));
+    }
+    path
+}
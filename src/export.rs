@@ -0,0 +1,143 @@
+// Rasterized maps for reviewing generation output outside the game
+//   Everything here runs without a window, so maps for a whole range of
+//   seeds can be dumped from the command line:
+//
+//   incremental_rl export-maps <first seed> <last seed> <folder>
+//       [--depth N] [--scale N] [--biome NAME] [--prefabs builtin|all]
+//
+//   By default only the built in prefabs are used, so the same seed always
+//   exports the same map. `--prefabs all` adds those in the `prefabs` folder
+//   of the working directory, as the game does.
+
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use macroquad::prelude::*;
+
+use crate::creature::Creature;
+use crate::game::Biome;
+use crate::game::EmbarkParams;
+use crate::game::TileMap;
+use crate::mapgen;
+use crate::prefab;
+use crate::prefab::Prefab;
+use crate::render::tile_color;
+
+pub const EXPORT_COMMAND: &str = "export-maps";
+
+const DEFAULT_SCALE: u16 = 4;
+
+// One `scale` by `scale` block per tile, in the embark screen's colors,
+//   with hazards, found or not, and then creatures drawn over their tiles.
+//   Fails if the image would be too big for `Image` to hold.
+pub fn tilemap_image(
+    tilemap: &TileMap,
+    creatures: &[Creature],
+    scale: u16,
+) -> Result<Image, String> {
+    let scale = scale as u32;
+    let width = tilemap.width() as u32 * scale;
+    let height = tilemap.height() as u32 * scale;
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(format!(
+            "A {} x {} image is too big; use a smaller scale",
+            width, height
+        ));
+    }
+    let mut image = Image::gen_image_color(width as u16, height as u16, BLACK);
+
+    let mut fill = |x: i32, y: i32, color: Color| {
+        for dy in 0..scale {
+            for dx in 0..scale {
+                // `export_png` flips rows on the way out, so write them upside down
+                let px = x as u32 * scale + dx;
+                let py = height - 1 - (y as u32 * scale + dy);
+                image.set_pixel(px, py, color);
+            }
+        }
+    };
+    for (pos, tile) in tilemap.tiles.iter() {
+        fill(pos.x, pos.y, tile_color(tile));
+    }
+    for (pos, hazard) in &tilemap.hazards {
+        fill(pos.x, pos.y, hazard.kind.stats().color);
+    }
+    for creature in creatures {
+        fill(creature.pos.x, creature.pos.y, creature.kind.stats().color);
+    }
+    Ok(image)
+}
+
+// Generate one level per seed, stamped with `prefabs`, and save each as
+//   `seed_<seed>_depth_<depth>.png`
+pub fn export_seeds(
+    seeds: RangeInclusive<usize>,
+    biome: Biome,
+    depth: u32,
+    scale: u16,
+    prefabs: &[Prefab],
+    dir: &Path,
+) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    for seed in seeds {
+        let params = EmbarkParams {
+            seed,
+            biome,
+            ..Default::default()
+        };
+        let level = mapgen::generate_level(&params, depth, prefabs);
+        let image = tilemap_image(&level.tilemap, &level.creatures, scale)?;
+        let path = dir.join(format!("seed_{}_depth_{}.png", seed, depth));
+        image.export_png(&path.to_string_lossy());
+        info!("Exported {}", path.display());
+    }
+    Ok(())
+}
+
+// Handle `export-maps`; `args` are everything after the command name
+pub fn run_command(args: &[String]) -> Result<(), String> {
+    let usage = format!(
+        "Usage: {} <first seed> <last seed> <folder> [--depth N] [--scale N] [--biome NAME] \
+         [--prefabs builtin|all]",
+        EXPORT_COMMAND
+    );
+    let [first, last, dir, options @ ..] = args else {
+        return Err(usage);
+    };
+    let first: usize = first.parse().map_err(|_| usage.clone())?;
+    let last: usize = last.parse().map_err(|_| usage.clone())?;
+    if first > last {
+        return Err(usage);
+    }
+
+    let mut depth = 0;
+    let mut scale = DEFAULT_SCALE;
+    let mut biome = Biome::Caverns;
+    let mut prefabs = prefab::builtin();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let value = options.next().ok_or_else(|| usage.clone())?;
+        match option.as_str() {
+            "--depth" => depth = value.parse().map_err(|_| usage.clone())?,
+            "--scale" => scale = value.parse().map_err(|_| usage.clone())?,
+            "--biome" => {
+                biome =
+                    Biome::from_name(value).ok_or_else(|| format!("Unknown biome `{}`", value))?;
+            }
+            "--prefabs" => {
+                prefabs = match value.as_str() {
+                    "builtin" => prefab::builtin(),
+                    "all" => prefab::load_all(),
+                    _ => return Err(usage),
+                };
+            }
+            _ => return Err(usage),
+        }
+    }
+    if scale == 0 {
+        return Err("Scale has to be at least 1".to_string());
+    }
+
+    export_seeds(first..=last, biome, depth, scale, &prefabs, Path::new(dir))
+}
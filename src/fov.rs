@@ -0,0 +1,107 @@
+// Field of view via recursive shadowcasting
+//   Each of the eight octants around the origin is scanned row by row,
+//   narrowing the lit slope range whenever an opaque tile casts a shadow.
+//   Map access goes through closures so the caller decides what blocks
+//   sight and what to do with the lit tiles.
+
+use crate::grid::Pos;
+
+// Transforms mapping octant-local (dx, dy) onto map (x, y) offsets
+const OCTANTS: [(i32, i32, i32, i32); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1),
+];
+
+pub fn compute_fov<O, V>(origin: Pos, radius: i32, is_opaque: O, mut mark_visible: V)
+where
+    O: Fn(Pos) -> bool,
+    V: FnMut(Pos),
+{
+    mark_visible(origin);
+    for octant in OCTANTS {
+        cast_light(
+            origin,
+            1,
+            1.0,
+            0.0,
+            radius,
+            octant,
+            &is_opaque,
+            &mut mark_visible,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cast_light<O, V>(
+    origin: Pos,
+    row: i32,
+    mut start_slope: f32,
+    end_slope: f32,
+    radius: i32,
+    octant: (i32, i32, i32, i32),
+    is_opaque: &O,
+    mark_visible: &mut V,
+) where
+    O: Fn(Pos) -> bool,
+    V: FnMut(Pos),
+{
+    if start_slope < end_slope {
+        return;
+    }
+    let (xx, xy, yx, yy) = octant;
+    let radius_sq = radius * radius;
+    let mut next_start_slope = start_slope;
+
+    for distance in row..=radius {
+        let dy = -distance;
+        let mut blocked = false;
+        for dx in -distance..=0 {
+            let pos = origin + Pos::new(dx * xx + dy * xy, dx * yx + dy * yy);
+            let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+            let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+
+            if start_slope < right_slope {
+                continue;
+            } else if end_slope > left_slope {
+                break;
+            }
+
+            if dx * dx + dy * dy <= radius_sq {
+                mark_visible(pos);
+            }
+
+            if blocked {
+                if is_opaque(pos) {
+                    next_start_slope = right_slope;
+                } else {
+                    blocked = false;
+                    start_slope = next_start_slope;
+                }
+            } else if is_opaque(pos) && distance < radius {
+                // Start of a shadow; light the part of the next row in front of it
+                blocked = true;
+                cast_light(
+                    origin,
+                    distance + 1,
+                    start_slope,
+                    left_slope,
+                    radius,
+                    octant,
+                    is_opaque,
+                    mark_visible,
+                );
+                next_start_slope = right_slope;
+            }
+        }
+        if blocked {
+            break;
+        }
+    }
+}
@@ -1,9 +1,46 @@
 use macroquad::prelude::*;
 use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ascii;
+use crate::creature;
+use crate::creature::Creature;
+use crate::editor::EditorState;
+use crate::editor::SaveTarget;
+use crate::fov;
+use crate::grid::Grid;
+use crate::grid::Pos;
+use crate::hazard::Hazard;
+use crate::hazard::HazardKind;
+use crate::hazard::Trigger;
+use crate::item;
+use crate::item::Item;
+use crate::item::ItemStats;
+use crate::item::Slot;
+use crate::mapgen;
+use crate::pathfinding;
+use crate::prefab;
+use crate::prefab::Prefab;
+use crate::turn;
+use crate::turn::Action;
 
 pub enum UiEvent {
+    AbandonLocation(Location),
+    CraftItem(usize),
+    EditorBrush(Option<&'static str>),
+    EditorFrequency(Biome, i32),
+    EditorLoad(PathBuf),
+    EditorNew,
+    EditorRename(String),
+    EditorResize(Pos),
+    EditorSave(SaveTarget),
     EmbarkLocation(Location),
+    EmbarkMap(PathBuf),
+    EquipItem(usize),
     KeyPress(KeyCode),
+    MouseWheel(f32),
+    TileClicked(Pos),
     Quit,
     Resize(f32, f32),
     StateTransition(GameScreen),
@@ -14,12 +51,75 @@ pub struct Resource {
     pub cur_val: f32,
     pub max_val: f32,
     pub color: Color,
+    // Cargo weight of a single unit when hauled out of an embark
+    pub weight: f32,
 }
 
+#[derive(Clone, Debug)]
 pub struct TileResource {
     pub key: String,
     pub color: Color,
     pub amount: f32,
+    pub max_amount: f32,
+    // Mining power required to break loose one yield of ore
+    pub hardness: f32,
+    // Mining power accumulated towards the next yield
+    pub progress: f32,
+}
+
+impl TileResource {
+    pub fn new(key: &str, color: Color, amount: f32, hardness: f32) -> Self {
+        TileResource {
+            key: key.to_string(),
+            color,
+            amount,
+            max_amount: amount,
+            hardness,
+            progress: 0.0,
+        }
+    }
+
+    // Fraction of the original deposit still in the ground, in [0, 1]
+    pub fn richness(&self) -> f32 {
+        if self.max_amount <= 0.0 {
+            return 0.0;
+        }
+        (self.amount / self.max_amount).clamp(0.0, 1.0)
+    }
+
+    // Pull up to `amount` units out of the deposit, returning what was extracted
+    fn extract(&mut self, amount: f32) -> f32 {
+        let taken = amount.min(self.amount).max(0.0);
+        self.amount -= taken;
+        taken
+    }
+
+    // Fraction of the way towards the next yield, in [0, 1]
+    pub fn progress_fraction(&self) -> f32 {
+        if self.hardness <= 0.0 {
+            return 0.0;
+        }
+        (self.progress / self.hardness).clamp(0.0, 1.0)
+    }
+
+    // Apply one mining action's worth of power, returning the number of
+    //   yields that broke loose
+    fn work(&mut self, power: f32) -> u32 {
+        if self.hardness <= 0.0 {
+            return 1;
+        }
+        self.progress += power;
+        let mut yields = 0;
+        while self.progress >= self.hardness {
+            self.progress -= self.hardness;
+            yields += 1;
+        }
+        yields
+    }
+
+    fn is_depleted(&self) -> bool {
+        self.amount <= 0.0
+    }
 }
 
 impl Resource {
@@ -39,43 +139,422 @@ impl Resource {
     }
 }
 
+#[derive(Clone, Debug)]
 pub enum Tile {
     Empty,
     Wall,
+    // Indestructible rock bounding every map
+    Bedrock,
+    Water,
+    Lava,
+    Door,
+    Rubble,
     Resource(TileResource),
+    // Landing pad the player must reach to leave with their haul
+    Extraction,
+    // Connections to the levels below and above
+    StairsDown,
+    StairsUp,
+    // Container of items, emptied when the player steps onto it
+    Cache(Vec<Item>),
+}
+
+// Gameplay properties shared by every tile of a type. Movement, sight and
+//   rendering all go through these rather than matching on the tile itself.
+pub struct TileProps {
+    pub name: &'static str,
+    pub color: Color,
+    pub passable: bool,
+    pub opaque: bool,
+    pub diggable: bool,
+    // Damage taken for every turn spent standing on the tile
+    pub hazard_damage: f32,
+    // Multiplier on the energy (and pathing cost) of stepping onto the tile
+    pub move_cost: u32,
+    // Digging power needed to tunnel through, if `diggable`
+    pub hardness: f32,
+    // Resource and amount left behind when the tile is dug out
+    pub byproduct: Option<(&'static str, f32)>,
+}
+
+const FLOOR_PROPS: TileProps = TileProps {
+    name: "floor",
+    color: WHITE,
+    passable: true,
+    opaque: false,
+    diggable: false,
+    hazard_damage: 0.0,
+    move_cost: 1,
+    hardness: 0.0,
+    byproduct: None,
+};
+
+const TILE_PROPS_EMPTY: TileProps = FLOOR_PROPS;
+const TILE_PROPS_WALL: TileProps = TileProps {
+    name: "rock",
+    color: BLACK,
+    passable: false,
+    opaque: true,
+    diggable: true,
+    hardness: 4.0,
+    byproduct: Some(("sandstone", 1.0)),
+    ..FLOOR_PROPS
+};
+const TILE_PROPS_BEDROCK: TileProps = TileProps {
+    name: "bedrock",
+    color: Color::new(0.05, 0.05, 0.1, 1.0),
+    passable: false,
+    opaque: true,
+    ..FLOOR_PROPS
+};
+const TILE_PROPS_WATER: TileProps = TileProps {
+    name: "water",
+    color: Color::new(0.3, 0.5, 0.9, 1.0),
+    move_cost: 3,
+    ..FLOOR_PROPS
+};
+const TILE_PROPS_LAVA: TileProps = TileProps {
+    name: "lava",
+    color: RED,
+    hazard_damage: 5.0,
+    move_cost: 2,
+    ..FLOOR_PROPS
+};
+const TILE_PROPS_DOOR: TileProps = TileProps {
+    name: "door",
+    color: DARKBROWN,
+    opaque: true,
+    ..FLOOR_PROPS
+};
+const TILE_PROPS_RUBBLE: TileProps = TileProps {
+    name: "rubble",
+    color: GRAY,
+    move_cost: 2,
+    ..FLOOR_PROPS
+};
+const TILE_PROPS_RESOURCE: TileProps = TileProps {
+    name: "deposit",
+    ..FLOOR_PROPS
+};
+const TILE_PROPS_EXTRACTION: TileProps = TileProps {
+    name: "landing pad",
+    color: VIOLET,
+    ..FLOOR_PROPS
+};
+const TILE_PROPS_STAIRS_DOWN: TileProps = TileProps {
+    name: "stairs down",
+    color: DARKBLUE,
+    ..FLOOR_PROPS
+};
+const TILE_PROPS_STAIRS_UP: TileProps = TileProps {
+    name: "stairs up",
+    color: SKYBLUE,
+    ..FLOOR_PROPS
+};
+const TILE_PROPS_CACHE: TileProps = TileProps {
+    name: "cache",
+    color: GOLD,
+    ..FLOOR_PROPS
+};
+
+impl Tile {
+    pub fn props(&self) -> &'static TileProps {
+        match self {
+            Tile::Empty => &TILE_PROPS_EMPTY,
+            Tile::Wall => &TILE_PROPS_WALL,
+            Tile::Bedrock => &TILE_PROPS_BEDROCK,
+            Tile::Water => &TILE_PROPS_WATER,
+            Tile::Lava => &TILE_PROPS_LAVA,
+            Tile::Door => &TILE_PROPS_DOOR,
+            Tile::Rubble => &TILE_PROPS_RUBBLE,
+            Tile::Resource(_) => &TILE_PROPS_RESOURCE,
+            Tile::Extraction => &TILE_PROPS_EXTRACTION,
+            Tile::StairsDown => &TILE_PROPS_STAIRS_DOWN,
+            Tile::StairsUp => &TILE_PROPS_STAIRS_UP,
+            Tile::Cache(_) => &TILE_PROPS_CACHE,
+        }
+    }
+}
+
+// What the player knows about a tile
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Visibility {
+    #[default]
+    Unseen,
+    Remembered,
+    Visible,
 }
 
-#[derive(Default)]
 pub struct TileMap {
-    pub width: f32,
-    pub height: f32,
-    pub tiles: Vec<Tile>,
+    pub tiles: Grid<Tile>,
+    pub visibility: Grid<Visibility>,
+    // Digging power put into partly dug tiles
+    pub dig_progress: HashMap<Pos, f32>,
+    pub hazards: HashMap<Pos, Hazard>,
+}
+
+impl TileMap {
+    pub fn new(width: usize, height: usize, fill: Tile) -> Self {
+        TileMap {
+            tiles: Grid::new(width, height, fill),
+            visibility: Grid::new(width, height, Visibility::Unseen),
+            dig_progress: HashMap::new(),
+            hazards: HashMap::new(),
+        }
+    }
+
+    // Wrap a freshly generated grid of tiles, with nothing seen or dug yet
+    pub fn from_tiles(tiles: Grid<Tile>) -> Self {
+        TileMap {
+            visibility: Grid::new(tiles.width(), tiles.height(), Visibility::Unseen),
+            tiles,
+            dig_progress: HashMap::new(),
+            hazards: HashMap::new(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.tiles.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.tiles.height()
+    }
+
+    pub fn get(&self, pos: Pos) -> Option<&Tile> {
+        self.tiles.get(pos)
+    }
+
+    pub fn set(&mut self, pos: Pos, tile: Tile) -> bool {
+        self.tiles.set(pos, tile)
+    }
+
+    // Fraction of the way through digging out `pos`, in [0, 1]
+    pub fn dig_fraction(&self, pos: Pos) -> f32 {
+        let Some(tile) = self.get(pos) else {
+            return 0.0;
+        };
+        let hardness = tile.props().hardness;
+        match self.dig_progress.get(&pos) {
+            Some(progress) if hardness > 0.0 => (progress / hardness).clamp(0.0, 1.0),
+            _ => 0.0,
+        }
+    }
+
+    // Put `power` into digging out `pos`. Returns true once the tile gives
+    //   way, leaving rubble behind.
+    pub fn dig(&mut self, pos: Pos, power: f32) -> bool {
+        let Some(tile) = self.get(pos) else {
+            return false;
+        };
+        let props = tile.props();
+        if !props.diggable {
+            return false;
+        }
+        let progress = self.dig_progress.entry(pos).or_insert(0.0);
+        *progress += power;
+        if *progress < props.hardness {
+            return false;
+        }
+        self.dig_progress.remove(&pos);
+        self.set(pos, Tile::Rubble)
+    }
+
+    pub fn visibility(&self, pos: Pos) -> Visibility {
+        self.visibility
+            .get(pos)
+            .copied()
+            .unwrap_or(Visibility::Unseen)
+    }
+
+    pub fn hazard(&self, pos: Pos) -> Option<&Hazard> {
+        self.hazards.get(&pos)
+    }
+
+    // Discover every hidden hazard in view within `radius` of `origin`,
+    //   returning each kind that turned up once. Without any detection
+    //   range nothing is found, not even what's underfoot.
+    pub fn detect_hazards(&mut self, origin: Pos, radius: u32) -> Vec<HazardKind> {
+        let mut found = vec![];
+        if radius == 0 {
+            return found;
+        }
+        for (pos, hazard) in &mut self.hazards {
+            let visible = self.visibility.get(*pos) == Some(&Visibility::Visible);
+            let in_range = pos.distance_sq(origin) <= (radius as i32).pow(2);
+            if !hazard.discovered && visible && in_range {
+                hazard.discovered = true;
+                if !found.contains(&hazard.kind) {
+                    found.push(hazard.kind);
+                }
+            }
+        }
+        found
+    }
+
+    // Recompute what can be seen from `origin`; anything previously in view
+    //   drops back to being remembered
+    pub fn update_visibility(&mut self, origin: Pos, radius: u32) {
+        for visibility in self.visibility.values_mut() {
+            if *visibility == Visibility::Visible {
+                *visibility = Visibility::Remembered;
+            }
+        }
+
+        let mut visible = vec![];
+        fov::compute_fov(
+            origin,
+            radius as i32,
+            |pos| self.get(pos).is_none_or(|tile| tile.props().opaque),
+            |pos| visible.push(pos),
+        );
+        for pos in visible {
+            self.visibility.set(pos, Visibility::Visible);
+        }
+    }
+}
+
+// How an embark came to an end, deciding how much of the hold makes it home
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmbarkOutcome {
+    Extracted,
+    Recalled,
+    Stranded,
+    Killed,
+}
+
+impl EmbarkOutcome {
+    // Fraction of the cargo hold banked on return to base
+    pub fn cargo_kept(&self) -> f32 {
+        match self {
+            EmbarkOutcome::Extracted => 1.0,
+            EmbarkOutcome::Recalled => 1.0 - EMERGENCY_RECALL_LOSS,
+            EmbarkOutcome::Stranded => 0.5,
+            EmbarkOutcome::Killed => 0.0,
+        }
+    }
+}
+
+// One level of an embark site
+pub struct Level {
+    pub tilemap: TileMap,
+    pub creatures: Vec<Creature>,
+}
+
+// Commands that keep acting on the player's behalf until done or interrupted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AutoMode {
+    Travel,
+    Explore,
+    Mine,
 }
 
 pub struct EmbarkState {
-    pub player_x: u32,
-    pub player_y: u32,
-    del_x: Option<f32>,
-    del_y: Option<f32>,
+    pub player: Pos,
+    // Step requested by the player this frame
+    step: Option<Pos>,
     pub tilemap: Option<TileMap>,
+    pub low_energy_warned: bool,
+    pub cargo: HashMap<String, f32>,
+    pub cargo_capacity: f32,
+    // Items picked up from caches, added to the inventory back at base
+    pub found_items: Vec<Item>,
+    pub outcome: EmbarkOutcome,
+    pub vision_radius: u32,
+    // Range at which hidden hazards in view are noticed
+    pub detection: u32,
+    // Index into `ZOOM_LEVELS`
+    pub zoom_level: usize,
+    pub creatures: Vec<Creature>,
+    pub hp: f32,
+    pub max_hp: f32,
+    pub attack: f32,
+    pub defense: f32,
+    // Banked toward the player's next action, see `turn`
+    pub action_points: i32,
+    // Remaining steps of automatic movement, next step last
+    pub travel_path: Vec<Pos>,
+    pub auto_mode: Option<AutoMode>,
+    last_auto_step: f64,
+    auto_mine_started: bool,
+    // What was in view last frame, to notice when something new shows up
+    seen_creatures: usize,
+    known_deposits: usize,
+    // Current level below the surface, and the levels the player has left behind
+    pub depth: u32,
+    visited_levels: HashMap<u32, Level>,
 }
 
 impl Default for EmbarkState {
     fn default() -> Self {
         EmbarkState {
-            player_x: 100 / 2,
-            player_y: 100 / 2,
-            del_x: None,
-            del_y: None,
+            player: Pos::new(100 / 2, 100 / 2),
+            step: None,
             tilemap: None,
+            low_energy_warned: false,
+            cargo: HashMap::new(),
+            cargo_capacity: 30.0,
+            found_items: vec![],
+            outcome: EmbarkOutcome::Extracted,
+            vision_radius: 8,
+            detection: 0,
+            zoom_level: 2,
+            creatures: vec![],
+            hp: 20.0,
+            max_hp: 20.0,
+            attack: 3.0,
+            defense: 1.0,
+            action_points: 0,
+            travel_path: vec![],
+            auto_mode: None,
+            last_auto_step: 0.0,
+            auto_mine_started: false,
+            seen_creatures: 0,
+            known_deposits: 0,
+            depth: 0,
+            visited_levels: HashMap::new(),
+        }
+    }
+}
+
+// Broad character of an embark site, shaping its terrain and set pieces
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Caverns,
+    Flooded,
+    Volcanic,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 3] = [Biome::Caverns, Biome::Flooded, Biome::Volcanic];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Biome::Caverns => "caverns",
+            Biome::Flooded => "flooded",
+            Biome::Volcanic => "volcanic",
         }
     }
+
+    pub fn from_name(name: &str) -> Option<Biome> {
+        Biome::ALL.into_iter().find(|biome| biome.name() == name)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct EmbarkParams {
     pub seed: usize,
-    pub dims: Vec2,
+    pub biome: Biome,
+    pub dims: (usize, usize),
+    // Energy drained per step, per mine action, and per idle tick spent on site
+    pub move_cost: f32,
+    pub mine_cost: f32,
+    pub tick_cost: f32,
+    pub dig_cost: f32,
+    // Ore taken out of a deposit each time mining it pays out
+    pub mine_yield: f32,
+    // Number of levels stacked below (and including) the surface
+    pub max_depth: u32,
     // TODO: specify embark probabilities for items
 }
 
@@ -83,7 +562,14 @@ impl Default for EmbarkParams {
     fn default() -> Self {
         EmbarkParams {
             seed: 0usize,
-            dims: vec2(100.0, 100.0),
+            biome: Biome::Caverns,
+            dims: (100, 100),
+            move_cost: 0.1,
+            mine_cost: 0.5,
+            tick_cost: 0.25,
+            dig_cost: 1.0,
+            mine_yield: 1.0,
+            max_depth: 4,
         }
     }
 }
@@ -95,9 +581,41 @@ pub enum Location {
 }
 
 impl Location {
-    fn generate_location(_state: &GameState) -> Location {
-        Location::Embark(EmbarkParams::default())
+    fn generate_location(state: &GameState) -> Location {
+        // Seeds double as site ids, so keep them unique
+        let seed = loop {
+            let seed = rand::rand() as usize;
+            if !state
+                .scouted_locations
+                .iter()
+                .any(|l| l.seed() == Some(seed))
+            {
+                break seed;
+            }
+        };
+        let biome = Biome::ALL[rand::gen_range(0, Biome::ALL.len())];
+        Location::Embark(EmbarkParams {
+            seed,
+            biome,
+            ..Default::default()
+        })
     }
+
+    pub fn seed(&self) -> Option<usize> {
+        match self {
+            Location::AtBase => None,
+            Location::Embark(params) => Some(params.seed),
+        }
+    }
+}
+
+// Everything changed at an embark site since it was generated, kept between
+//   visits so the player picks up where they left off
+#[derive(Default)]
+pub struct Site {
+    pub levels: HashMap<u32, Level>,
+    // Where the player lands, for sites laid out by hand rather than generated
+    pub spawn: Option<Pos>,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -105,8 +623,31 @@ pub enum GameScreen {
     _Title,
     Idle,
     Embark,
+    Editor,
 }
 
+// Floors on mining power and on energy costs, however the player is equipped
+const MIN_MINING_POWER: f32 = 0.5;
+const MIN_ENERGY_FACTOR: f32 = 0.25;
+// Share of the player's speed lost to a completely full cargo hold
+const FULL_LOAD_SLOWDOWN: f32 = 0.4;
+// Odds that working unstable rock brings it down
+const CAVE_IN_CHANCE: f32 = 0.3;
+// Extra pathing cost of a tile with a known hazard, as a number of floor tiles
+//   the player would rather walk around it
+const KNOWN_HAZARD_COST: u32 = 50;
+
+// Fraction of max energy below which an embark starts warning the player
+pub const LOW_ENERGY_FRACTION: f32 = 0.25;
+// Fraction of the cargo hold given up to call in an emergency recall
+pub const EMERGENCY_RECALL_LOSS: f32 = 0.25;
+// Embark camera magnification, relative to fitting the whole map on screen
+pub const ZOOM_LEVELS: [f32; 6] = [1.0, 2.0, 3.0, 4.0, 6.0, 8.0];
+// Seconds between actions taken automatically, e.g. when travelling
+pub const AUTO_STEP_DELAY: f64 = 0.08;
+// Number of player-facing messages kept around
+pub const MAX_MESSAGES: usize = 100;
+
 pub struct GameState {
     pub exit_requested: bool,
 
@@ -124,10 +665,20 @@ pub struct GameState {
     pub unlocked_resources: HashSet<String>,
     pub cur_location: Location,
     pub scouted_locations: Vec<Location>,
+    // Sites the player has visited, keyed by location seed
+    pub sites: HashMap<usize, Site>,
+    pub messages: Vec<String>,
+    // Set pieces available to the level generator
+    pub prefabs: Vec<Prefab>,
 
     // Embark specific state
     pub embark_state: EmbarkState,
     pub mine_requested: bool,
+    // What the player takes on embarks, and what's left in storage at base
+    pub equipment: HashMap<Slot, Item>,
+    pub inventory: Vec<Item>,
+
+    pub editor_state: EditorState,
 }
 
 impl Default for GameState {
@@ -139,6 +690,7 @@ impl Default for GameState {
                 cur_val: 100.0,
                 max_val: 100.0,
                 color: WHITE,
+                weight: 0.0,
             },
         );
         default_resources.insert(
@@ -147,6 +699,7 @@ impl Default for GameState {
                 cur_val: 0.0,
                 max_val: 100.0,
                 color: BROWN,
+                weight: 1.0,
             },
         );
         default_resources.insert(
@@ -155,6 +708,7 @@ impl Default for GameState {
                 cur_val: 0.0,
                 max_val: 100.0,
                 color: BLUE,
+                weight: 1.5,
             },
         );
         default_resources.insert(
@@ -163,6 +717,7 @@ impl Default for GameState {
                 cur_val: 0.0,
                 max_val: 100.0,
                 color: BEIGE,
+                weight: 2.0,
             },
         );
 
@@ -182,38 +737,604 @@ impl Default for GameState {
 
             cur_location: Location::AtBase,
             scouted_locations: vec![],
+            sites: HashMap::new(),
+            messages: vec![],
+            prefabs: prefab::load_all(),
             resources: default_resources,
             unlocked_resources: default_unlocked,
 
             embark_state: EmbarkState::default(),
             mine_requested: false,
+            equipment: item::starting_equipment()
+                .into_iter()
+                .map(|item| (item.slot, item))
+                .collect(),
+            inventory: vec![],
+
+            editor_state: EditorState::default(),
         }
     }
 }
 
 impl GameState {
+    // Combined bonuses of everything equipped
+    pub fn equipment_stats(&self) -> ItemStats {
+        self.equipment
+            .values()
+            .fold(ItemStats::default(), |total, item| total + item.stats)
+    }
+
+    // Mining power applied to a deposit per mine action
+    pub fn mining_power(&self) -> f32 {
+        self.equipment_stats().mining_power.max(MIN_MINING_POWER)
+    }
+
+    // Multiplier on every energy cost paid on an embark
+    pub fn energy_factor(&self) -> f32 {
+        (1.0 - self.equipment_stats().energy_efficiency).max(MIN_ENERGY_FACTOR)
+    }
+
+    pub fn can_afford(&self, cost: &[(&str, f32)]) -> bool {
+        cost.iter()
+            .all(|(key, amount)| self.resources.get(*key).unwrap().cur_val >= *amount)
+    }
+
+    fn craft_item(&mut self, index: usize) {
+        let Some(recipe) = item::RECIPES.get(index) else {
+            return;
+        };
+        if !self.can_afford(recipe.cost) {
+            self.log_message(format!("Not enough resources to build a {}", recipe.name));
+            return;
+        }
+        for (key, amount) in recipe.cost {
+            self.resources.get_mut(*key).unwrap().add_or_max(-amount);
+        }
+        self.inventory.push(recipe.item());
+        self.log_message(format!("Built a {}", recipe.name));
+    }
+
+    // Swap an item from storage into its slot. Gear can only be changed at base.
+    fn equip_item(&mut self, index: usize) {
+        if self.cur_location != Location::AtBase || index >= self.inventory.len() {
+            return;
+        }
+        let item = self.inventory.remove(index);
+        self.log_message(format!("Equipped {}", item.name));
+        if let Some(old) = self.equipment.insert(item.slot, item) {
+            self.inventory.push(old);
+        }
+    }
+
     pub fn idle_tick(&mut self) {
         // Handle Energy
         //   Currently, when at base, recharge 1.0 unit per tick
+        //   When on an embark, drain at the location's rate
         match self.cur_location {
             Location::AtBase => {
                 self.resources.get_mut("energy").unwrap().add_or_max(1.0);
             }
-            Location::Embark(_val) => {
-                // Currently, do nothing
+            Location::Embark(params) => {
+                let cost = params.tick_cost * self.energy_factor();
+                self.resources.get_mut("energy").unwrap().add_or_max(-cost);
+            }
+        }
+    }
+
+    // Pathing cost of stepping onto `pos`, if the player knows it and can
+    //   walk onto it without the tile itself hurting them. Hazards they know
+    //   about are crossed only when there's no way around.
+    fn known_step_cost(tilemap: &TileMap, pos: Pos) -> Option<u32> {
+        if tilemap.visibility(pos) == Visibility::Unseen {
+            return None;
+        }
+        let tile = tilemap.get(pos).filter(|tile| mapgen::is_walkable(tile))?;
+        let hazard_cost = match tilemap.hazard(pos) {
+            Some(hazard) if hazard.discovered => KNOWN_HAZARD_COST,
+            _ => 0,
+        };
+        Some(tile.props().move_cost + hazard_cost)
+    }
+
+    // Plan a route to `goal` over tiles the player knows to be passable
+    fn start_travel(&mut self, goal: Pos) {
+        let Some(tilemap) = &self.embark_state.tilemap else {
+            return;
+        };
+        let player = self.embark_state.player;
+        let path = pathfinding::find_path(tilemap, player, goal, |pos| {
+            Self::known_step_cost(tilemap, pos)
+        });
+        match path {
+            Some(mut path) => {
+                path.reverse();
+                self.embark_state.travel_path = path;
+                self.embark_state.auto_mode = Some(AutoMode::Travel);
+            }
+            None => {
+                self.log_message("No known route there".to_string());
             }
         }
     }
 
+    // Auto-explore and auto-mine refuse to start with danger in sight
+    fn start_auto_mode(&mut self, mode: AutoMode) {
+        if self.embark_state.seen_creatures > 0 {
+            self.log_message("Not with a creature in sight!".to_string());
+            return;
+        }
+        if self.is_low_energy() {
+            self.log_message("Too little energy to go on autopilot".to_string());
+            return;
+        }
+        self.embark_state.travel_path.clear();
+        self.embark_state.auto_mode = Some(mode);
+        self.embark_state.auto_mine_started = false;
+    }
+
+    fn stop_auto_mode(&mut self, message: &str) {
+        self.embark_state.travel_path.clear();
+        self.embark_state.auto_mode = None;
+        self.embark_state.auto_mine_started = false;
+        self.log_message(message.to_string());
+    }
+
+    // Advance whichever automatic command is running by at most one action
+    fn run_auto_mode(&mut self) {
+        let Some(mode) = self.embark_state.auto_mode else {
+            return;
+        };
+        if mode != AutoMode::Travel && self.is_low_energy() {
+            self.stop_auto_mode("Energy low, stopping");
+            return;
+        }
+        let now = get_time();
+        if now - self.embark_state.last_auto_step < AUTO_STEP_DELAY {
+            return;
+        }
+        if !self.embark_state.travel_path.is_empty() {
+            self.follow_travel_path();
+            return;
+        }
+
+        let Location::Embark(params) = self.cur_location else {
+            return;
+        };
+        let Some(tilemap) = &self.embark_state.tilemap else {
+            return;
+        };
+        let player = self.embark_state.player;
+        match mode {
+            AutoMode::Travel => {
+                self.embark_state.auto_mode = None;
+            }
+            AutoMode::Explore => {
+                // Head for the closest known floor that borders the unknown
+                let path = pathfinding::path_to_nearest(
+                    tilemap,
+                    player,
+                    |pos| Self::known_step_cost(tilemap, pos),
+                    |pos| {
+                        tilemap
+                            .tiles
+                            .neighbors(pos)
+                            .any(|next| tilemap.visibility(next) == Visibility::Unseen)
+                    },
+                );
+                match path {
+                    Some(mut path) if !path.is_empty() => {
+                        path.reverse();
+                        self.embark_state.travel_path = path;
+                    }
+                    _ => self.stop_auto_mode("Nothing left to explore"),
+                }
+            }
+            AutoMode::Mine => {
+                if let Some(Tile::Resource(resource)) = tilemap.get(player) {
+                    let weight = self.resources.get(&resource.key).unwrap().weight;
+                    let free_weight = self.embark_state.cargo_capacity - self.cargo_weight();
+                    if free_weight < weight * params.mine_yield {
+                        self.stop_auto_mode("Cargo hold is full, stopping");
+                    } else {
+                        self.mine_requested = true;
+                        self.embark_state.last_auto_step = now;
+                        self.embark_state.auto_mine_started = true;
+                    }
+                } else if self.embark_state.auto_mine_started {
+                    self.stop_auto_mode("Deposit mined out");
+                } else {
+                    let path = pathfinding::path_to_nearest(
+                        tilemap,
+                        player,
+                        |pos| Self::known_step_cost(tilemap, pos),
+                        |pos| matches!(tilemap.get(pos), Some(Tile::Resource(_))),
+                    );
+                    match path {
+                        Some(mut path) => {
+                            path.reverse();
+                            self.embark_state.travel_path = path;
+                        }
+                        None => self.stop_auto_mode("No known deposits to mine"),
+                    }
+                }
+            }
+        }
+    }
+
+    // Queue up the next step of the travel path
+    fn follow_travel_path(&mut self) {
+        let Some(&next) = self.embark_state.travel_path.last() else {
+            return;
+        };
+
+        let player = self.embark_state.player;
+        let occupied = self
+            .embark_state
+            .creatures
+            .iter()
+            .any(|creature| creature.pos == next);
+        if player.distance_sq(next) != 1 || occupied {
+            self.stop_auto_mode("Something is in the way");
+            return;
+        }
+
+        self.embark_state.travel_path.pop();
+        self.embark_state.last_auto_step = get_time();
+        self.embark_state.step = Some(next - player);
+    }
+
+    // Stop automatic movement when a creature comes into view. Travelling to
+    //   a clicked tile also stops for newly discovered deposits.
+    fn check_auto_interrupts(&mut self) {
+        let Some(tilemap) = &self.embark_state.tilemap else {
+            return;
+        };
+        let seen_creatures = self
+            .embark_state
+            .creatures
+            .iter()
+            .filter(|creature| tilemap.visibility(creature.pos) == Visibility::Visible)
+            .count();
+        let known_deposits = tilemap
+            .tiles
+            .values()
+            .zip(tilemap.visibility.values())
+            .filter(|(tile, visibility)| {
+                matches!(tile, Tile::Resource(_)) && **visibility != Visibility::Unseen
+            })
+            .count();
+
+        let spotted_creature = seen_creatures > self.embark_state.seen_creatures;
+        let spotted_deposit = known_deposits > self.embark_state.known_deposits;
+        self.embark_state.seen_creatures = seen_creatures;
+        self.embark_state.known_deposits = known_deposits;
+
+        match self.embark_state.auto_mode {
+            Some(_) if spotted_creature => {
+                self.stop_auto_mode("You spot a creature and stop");
+            }
+            Some(AutoMode::Travel) if spotted_deposit => {
+                self.stop_auto_mode("You spot a deposit and stop");
+            }
+            _ => {}
+        }
+    }
+
+    // Hurt the player for ending their turn somewhere dangerous
+    fn apply_tile_hazard(&mut self) {
+        let Some(tilemap) = &self.embark_state.tilemap else {
+            return;
+        };
+        let Some(tile) = tilemap.get(self.embark_state.player) else {
+            return;
+        };
+        let props = tile.props();
+        if props.hazard_damage <= 0.0 {
+            return;
+        }
+
+        self.embark_state.hp -= props.hazard_damage;
+        self.log_message(format!(
+            "The {} burns you for {:.0} damage",
+            props.name, props.hazard_damage
+        ));
+        if self.embark_state.hp <= 0.0 && self.next_game_mode.is_none() {
+            self.log_message(format!("You were killed by {}!", props.name));
+            self.embark_state.outcome = EmbarkOutcome::Killed;
+            self.next_game_mode = Some(GameScreen::Idle);
+        }
+    }
+
+    // Set off the hazards the player's action ran into: whatever is underfoot,
+    //   and unstable rock at `worked`, the tile they mined or dug at
+    fn trigger_hazards(&mut self, entered: bool, worked: Option<Pos>) {
+        let Some(tilemap) = &mut self.embark_state.tilemap else {
+            return;
+        };
+        let player = self.embark_state.player;
+        let mut triggered = vec![];
+        if let Some(hazard) = tilemap.hazards.get_mut(&player) {
+            let trigger = hazard.kind.stats().trigger;
+            if trigger == Trigger::Stand || (trigger == Trigger::Enter && entered) {
+                hazard.discovered = true;
+                triggered.push(hazard.kind);
+            }
+        }
+        if let Some(pos) = worked {
+            let unstable = tilemap
+                .hazard(pos)
+                .is_some_and(|hazard| hazard.kind.stats().trigger == Trigger::Mine);
+            if unstable && rand::gen_range(0.0, 1.0) < CAVE_IN_CHANCE {
+                // The ceiling comes down around the worked tile
+                tilemap.hazards.remove(&pos);
+                let around: Vec<Pos> = tilemap.tiles.neighbors(pos).collect();
+                for next in around {
+                    if next != player && matches!(tilemap.get(next), Some(Tile::Empty)) {
+                        tilemap.set(next, Tile::Rubble);
+                    }
+                }
+                triggered.push(HazardKind::CaveIn);
+            } else if unstable && !matches!(tilemap.get(pos), Some(Tile::Wall | Tile::Resource(_)))
+            {
+                // Nothing left to fall once the rock is dug or mined out
+                tilemap.hazards.remove(&pos);
+            }
+        }
+
+        for kind in triggered {
+            let stats = kind.stats();
+            let mut message = stats.message.to_string();
+            if stats.damage > 0.0 {
+                self.embark_state.hp -= stats.damage;
+                message += &format!(", taking {:.0} damage", stats.damage);
+            }
+            if stats.energy_drain > 0.0 {
+                let drain = stats.energy_drain * self.energy_factor();
+                self.resources.get_mut("energy").unwrap().add_or_max(-drain);
+                message += &format!(", losing {:.1} energy", drain);
+            }
+            self.log_message(message);
+            if self.embark_state.hp <= 0.0 && self.next_game_mode.is_none() {
+                self.log_message(format!("You were killed by the {}!", stats.name));
+                self.embark_state.outcome = EmbarkOutcome::Killed;
+                self.next_game_mode = Some(GameScreen::Idle);
+            }
+        }
+    }
+
+    // Charge the player `cost` action points, then run the clock until they
+    //   can act again, letting creatures take every turn they earn meanwhile
+    fn pass_time(&mut self, cost: i32) {
+        self.embark_state.action_points -= cost;
+        let speed = self.player_speed();
+        while self.embark_state.action_points < 0 && self.next_game_mode.is_none() {
+            self.embark_state.action_points += speed;
+            for creature in &mut self.embark_state.creatures {
+                creature.action_points += creature.kind.stats().speed;
+            }
+            self.advance_creatures();
+        }
+    }
+
+    // Let every creature spend the action points it has banked
+    fn advance_creatures(&mut self) {
+        let tilemap = match &self.embark_state.tilemap {
+            Some(tilemap) => tilemap,
+            None => return,
+        };
+        let player = self.embark_state.player;
+        let creatures = &mut self.embark_state.creatures;
+
+        let mut messages = vec![];
+        'creatures: for i in 0..creatures.len() {
+            while creatures[i].action_points >= 0 {
+                let others: HashSet<Pos> = creatures
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, creature)| creature.pos)
+                    .collect();
+                let is_blocked = |pos| pos == player || others.contains(&pos);
+                let from = creatures[i].pos;
+                let turn = creatures[i].take_turn(tilemap, player, is_blocked);
+                let action = if turn.attacked {
+                    Action::Attack
+                } else if creatures[i].pos != from {
+                    let to = creatures[i].pos;
+                    Action::Move(tilemap.get(to).map_or(1, |tile| tile.props().move_cost))
+                } else {
+                    Action::Wait
+                };
+                creatures[i].action_points -= action.cost();
+                if let Some(message) = turn.message {
+                    messages.push(message);
+                }
+                if turn.attacked {
+                    let stats = creatures[i].kind.stats();
+                    let damage = creature::roll_damage(stats.attack, self.embark_state.defense);
+                    self.embark_state.hp -= damage;
+                    messages.push(format!(
+                        "The {} hits you for {:.0} damage",
+                        stats.name, damage
+                    ));
+                    if self.embark_state.hp <= 0.0 {
+                        messages.push(format!("You were killed by a {}!", stats.name));
+                        break 'creatures;
+                    }
+                }
+            }
+        }
+
+        for message in messages {
+            self.log_message(message);
+        }
+
+        if self.embark_state.hp <= 0.0 && self.next_game_mode.is_none() {
+            self.embark_state.outcome = EmbarkOutcome::Killed;
+            self.next_game_mode = Some(GameScreen::Idle);
+        }
+    }
+
+    // Bump-attack the creature at `target`, removing it if it dies
+    fn player_attack(&mut self, target: usize) {
+        let creature = &mut self.embark_state.creatures[target];
+        let stats = creature.kind.stats();
+        let damage = creature::roll_damage(self.embark_state.attack, stats.defense);
+        creature.hp -= damage;
+        let message = if creature.hp <= 0.0 {
+            self.embark_state.creatures.remove(target);
+            format!("You hit the {} for {:.0}, killing it", stats.name, damage)
+        } else {
+            format!("You hit the {} for {:.0} damage", stats.name, damage)
+        };
+        self.log_message(message);
+    }
+
+    // Record a message for the player, mirroring it to the log
+    pub fn log_message(&mut self, message: String) {
+        info!("{}", message);
+        self.messages.push(message);
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+    }
+
+    // Action points the player banks per tick. A loaded hold slows them
+    //   down, to `1 - FULL_LOAD_SLOWDOWN` of normal speed when it's full.
+    pub fn player_speed(&self) -> i32 {
+        let load = (self.cargo_weight() / self.embark_state.cargo_capacity).clamp(0.0, 1.0);
+        let speed = turn::NORMAL_SPEED as f32 * (1.0 - FULL_LOAD_SLOWDOWN * load);
+        (speed.round() as i32).max(1)
+    }
+
+    pub fn cargo_weight(&self) -> f32 {
+        self.embark_state
+            .cargo
+            .iter()
+            .map(|(key, amount)| amount * self.resources.get(key).unwrap().weight)
+            .sum()
+    }
+
+    // Stow up to `amount` of `key` in the cargo hold, returning how much fit
+    fn add_to_cargo(&mut self, key: &str, amount: f32) -> f32 {
+        let weight = self.resources.get(key).unwrap().weight;
+        let free_weight = (self.embark_state.cargo_capacity - self.cargo_weight()).max(0.0);
+        let stowed = if weight > 0.0 {
+            amount.min(free_weight / weight)
+        } else {
+            amount
+        };
+        if stowed > 0.0 {
+            *self
+                .embark_state
+                .cargo
+                .entry(key.to_string())
+                .or_insert(0.0) += stowed;
+        }
+        if stowed < amount {
+            self.log_message(format!("No room in the hold for all the {}", key));
+        }
+        stowed
+    }
+
+    // Move the cargo hold into the base stockpile, keeping only the fraction
+    //   the embark's outcome allows
+    fn bank_cargo(&mut self) {
+        let kept = self.embark_state.outcome.cargo_kept();
+        let cargo = std::mem::take(&mut self.embark_state.cargo);
+        for (key, amount) in cargo {
+            let banked = amount * kept;
+            if banked <= 0.0 {
+                continue;
+            }
+            self.resources.get_mut(&key).unwrap().add_or_max(banked);
+            if !self.unlocked_resources.contains(&key) {
+                info!("Unlocking {}", key);
+                self.unlocked_resources.insert(key.clone());
+            }
+            self.log_message(format!("Banked {:.3} {}", banked, key));
+        }
+        if kept < 1.0 {
+            self.log_message(format!(
+                "Lost {:.0}% of the cargo hold on the way back",
+                100.0 * (1.0 - kept)
+            ));
+        }
+
+        // Found items are all or nothing: they come home unless the player doesn't
+        let found = std::mem::take(&mut self.embark_state.found_items);
+        if kept <= 0.0 {
+            if !found.is_empty() {
+                self.log_message(format!("Lost {} found items", found.len()));
+            }
+            return;
+        }
+        for item in found {
+            self.log_message(format!("Brought back {}", item.name));
+            self.inventory.push(item);
+        }
+    }
+
+    pub fn is_low_energy(&self) -> bool {
+        let energy = self.resources.get("energy").unwrap();
+        energy.cur_val < LOW_ENERGY_FRACTION * energy.max_val
+    }
+
+    // Warn once per embark on low energy, and haul the player back to base
+    //   once the batteries are flat
+    fn check_embark_energy(&mut self) {
+        if self.next_game_mode.is_some() {
+            return;
+        }
+        if self.resources.get("energy").unwrap().cur_val <= 0.0 {
+            self.log_message("Batteries depleted! Recalling to base...".to_string());
+            self.embark_state.outcome = EmbarkOutcome::Stranded;
+            self.next_game_mode = Some(GameScreen::Idle);
+        } else if self.is_low_energy() && !self.embark_state.low_energy_warned {
+            self.embark_state.low_energy_warned = true;
+            self.log_message("Warning: energy low, consider returning to base".to_string());
+        }
+    }
+
     fn survey_surroundings(&mut self) {
         // TODO: Check current unlocks for possible locations
         let location = Location::generate_location(self);
         self.scouted_locations.push(location);
     }
 
-    fn generate_tilemap(&self) -> TileMap {
-        let mut tilemap = TileMap::default();
+    // Scout a new site whose top level is the fixed map at `path`, and set
+    //   off for it. It stays on the embark list like any other site.
+    fn embark_fixed_map(&mut self, path: &Path) {
+        let map = match prefab::load_file(path) {
+            Ok(map) => map,
+            Err(err) => {
+                self.log_message(format!("Couldn't load {}: {}", path.display(), err));
+                return;
+            }
+        };
+        let location = Location::generate_location(self);
+        let Location::Embark(params) = location else {
+            return;
+        };
+        let level = mapgen::check_fixed_map(&map).and_then(|_| mapgen::fixed_level(&params, &map));
+        let (level, spawn) = match level {
+            Ok(level) => level,
+            Err(err) => {
+                self.log_message(format!("Can't play {}: {}", path.display(), err));
+                return;
+            }
+        };
+        let site = Site {
+            levels: HashMap::from([(0, level)]),
+            spawn: Some(spawn),
+        };
+        self.sites.insert(params.seed, site);
+        self.scouted_locations.push(location);
+        self.next_location = Some(location);
+        self.next_game_mode = Some(GameScreen::Embark);
+    }
 
+    fn generate_level(&self, depth: u32) -> Level {
         let embark_params;
         if let Location::Embark(params) = self.cur_location {
             embark_params = params;
@@ -221,72 +1342,206 @@ impl GameState {
             todo!("Handle cur_location/tilemap gen disagreement");
         }
 
-        tilemap.width = embark_params.dims.x;
-        tilemap.height = embark_params.dims.y;
+        mapgen::generate_level(&embark_params, depth, &self.prefabs)
+    }
 
-        for x in 0..(tilemap.width as u32) {
-            for y in 0..(tilemap.height as u32) {
-                if y == 0
-                    || (y == (tilemap.height as u32) - 1)
-                    || x == 0
-                    || (x == (tilemap.width as u32) - 1)
-                // || x == y
-                {
-                    tilemap.tiles.push(Tile::Wall);
-                } else {
-                    // TODO: roll dice on whether tile is empty or resourced
-                    if x == 5 && y == 5 {
-                        tilemap.tiles.push(Tile::Resource(TileResource {
-                            key: "iron_ore".to_string(),
-                            color: BLUE,
-                            amount: 10.0,
-                        }));
-                    } else if x == 10 && y == 10 {
-                        tilemap.tiles.push(Tile::Resource(TileResource {
-                            key: "bauxite".to_string(),
-                            color: BROWN,
-                            amount: 10.0,
-                        }));
-                    } else if x == 15 && y == 15 {
-                        tilemap.tiles.push(Tile::Resource(TileResource {
-                            key: "sandstone".to_string(),
-                            color: BEIGE,
-                            amount: 10.0,
-                        }));
-                    } else {
-                        tilemap.tiles.push(Tile::Empty);
-                    }
-                }
-            }
+    // Keep every level of the current site around for the next visit
+    fn store_site(&mut self) {
+        let Some(seed) = self.cur_location.seed() else {
+            return;
+        };
+        let mut levels = std::mem::take(&mut self.embark_state.visited_levels);
+        if let Some(tilemap) = self.embark_state.tilemap.take() {
+            let creatures = std::mem::take(&mut self.embark_state.creatures);
+            levels.insert(self.embark_state.depth, Level { tilemap, creatures });
         }
+        self.sites.entry(seed).or_default().levels = levels;
+    }
 
-        tilemap
+    // Take the stairs under the player, if any, `delta` levels down
+    fn change_level(&mut self, delta: i32) {
+        let player = self.embark_state.player;
+        let on_stairs = match &self.embark_state.tilemap {
+            Some(tilemap) => matches!(
+                (tilemap.get(player), delta),
+                (Some(Tile::StairsDown), 1) | (Some(Tile::StairsUp), -1)
+            ),
+            None => false,
+        };
+        if !on_stairs {
+            let direction = if delta > 0 { "down" } else { "up" };
+            self.log_message(format!("There are no stairs {} here", direction));
+            return;
+        }
+        let Location::Embark(params) = self.cur_location else {
+            return;
+        };
+
+        // Stash the level being left so it can be returned to as it was
+        let old_depth = self.embark_state.depth;
+        if let Some(tilemap) = self.embark_state.tilemap.take() {
+            let creatures = std::mem::take(&mut self.embark_state.creatures);
+            self.embark_state
+                .visited_levels
+                .insert(old_depth, Level { tilemap, creatures });
+        }
+
+        let depth = old_depth.checked_add_signed(delta).unwrap();
+        let level = match self.embark_state.visited_levels.remove(&depth) {
+            Some(level) => level,
+            None => self.generate_level(depth),
+        };
+
+        // Arrive on the matching stairs of the new level
+        let arrival = if delta > 0 {
+            mapgen::spawn_point(&params)
+        } else {
+            level
+                .tilemap
+                .tiles
+                .find(|tile| matches!(tile, Tile::StairsDown))
+                .unwrap_or_else(|| mapgen::spawn_point(&params))
+        };
+        self.embark_state.player = arrival;
+        self.embark_state.tilemap = Some(level.tilemap);
+        self.embark_state.creatures = level.creatures;
+        self.embark_state.depth = depth;
+
+        self.embark_state.travel_path.clear();
+        self.embark_state.auto_mode = None;
+        self.embark_state.seen_creatures = 0;
+        self.embark_state.known_deposits = 0;
+        let cost = params.move_cost * self.energy_factor();
+        self.resources.get_mut("energy").unwrap().add_or_max(-cost);
+
+        if delta > 0 {
+            self.log_message(format!("You descend to depth {}", depth));
+        } else if depth == 0 {
+            self.log_message("You climb back to the surface".to_string());
+        } else {
+            self.log_message(format!("You climb up to depth {}", depth));
+        }
+    }
+
+    // Leave the embark with the full hold, but only from a landing pad
+    fn request_extraction(&mut self) {
+        let on_pad = match &self.embark_state.tilemap {
+            Some(tilemap) => matches!(
+                tilemap.get(self.embark_state.player),
+                Some(Tile::Extraction)
+            ),
+            None => false,
+        };
+        if on_pad {
+            self.embark_state.outcome = EmbarkOutcome::Extracted;
+            self.next_game_mode = Some(GameScreen::Idle);
+        } else {
+            self.log_message(
+                "Reach a landing pad to extract, or press R for an emergency recall".to_string(),
+            );
+        }
+    }
+
+    // Dump the current level as text, to capture whatever it's doing wrong
+    fn save_snapshot(&mut self) {
+        let Some(tilemap) = &self.embark_state.tilemap else {
+            return;
+        };
+        let text = ascii::to_ascii(
+            tilemap,
+            Some(self.embark_state.player),
+            &self.embark_state.creatures,
+        );
+        let seed = self.cur_location.seed().unwrap_or(0);
+        let path = Path::new(ascii::SNAPSHOT_DIR)
+            .join(format!("{:x}_{}.txt", seed, self.embark_state.depth));
+        match fs::create_dir_all(ascii::SNAPSHOT_DIR).and_then(|_| fs::write(&path, text)) {
+            Ok(()) => self.log_message(format!("Saved snapshot to {}", path.display())),
+            Err(err) => self.log_message(format!("Couldn't save snapshot: {}", err)),
+        }
+    }
+
+    fn zoom_in(&mut self) {
+        let zoom_level = &mut self.embark_state.zoom_level;
+        *zoom_level = (*zoom_level + 1).min(ZOOM_LEVELS.len() - 1);
+    }
+
+    fn zoom_out(&mut self) {
+        let zoom_level = &mut self.embark_state.zoom_level;
+        *zoom_level = zoom_level.saturating_sub(1);
     }
 
     // Translate received keypresses into a potential state update
     pub fn process_keypress(&mut self, keycode: KeyCode) {
         match keycode {
+            // Letters typed into the editor's name field aren't hotkeys
+            KeyCode::I | KeyCode::Q if self.game_mode == GameScreen::Editor => {}
             KeyCode::I => {
-                self.next_game_mode = Some(GameScreen::Idle);
+                if let GameScreen::Embark = self.game_mode {
+                    self.request_extraction();
+                } else {
+                    self.next_game_mode = Some(GameScreen::Idle);
+                }
+            }
+            KeyCode::R => {
+                if let GameScreen::Embark = self.game_mode {
+                    self.log_message(format!(
+                        "Emergency recall! Jettisoning {:.0}% of the cargo hold",
+                        100.0 * EMERGENCY_RECALL_LOSS
+                    ));
+                    self.embark_state.outcome = EmbarkOutcome::Recalled;
+                    self.next_game_mode = Some(GameScreen::Idle);
+                }
             }
             KeyCode::Q => {
                 self.exit_requested = true;
             }
             KeyCode::Up => {
-                self.embark_state.del_y = Some(-1.0);
+                self.embark_state.step = Some(Pos::new(0, -1));
             }
             KeyCode::Down => {
-                self.embark_state.del_y = Some(1.0);
+                self.embark_state.step = Some(Pos::new(0, 1));
             }
             KeyCode::Left => {
-                self.embark_state.del_x = Some(-1.0);
+                self.embark_state.step = Some(Pos::new(-1, 0));
             }
             KeyCode::Right => {
-                self.embark_state.del_x = Some(1.0);
+                self.embark_state.step = Some(Pos::new(1, 0));
             }
             KeyCode::Space => {
                 self.mine_requested = true;
             }
+            KeyCode::X => {
+                if let GameScreen::Embark = self.game_mode {
+                    self.start_auto_mode(AutoMode::Explore);
+                }
+            }
+            KeyCode::M => {
+                if let GameScreen::Embark = self.game_mode {
+                    self.start_auto_mode(AutoMode::Mine);
+                }
+            }
+            KeyCode::Period => {
+                if let GameScreen::Embark = self.game_mode {
+                    self.change_level(1);
+                }
+            }
+            KeyCode::Comma => {
+                if let GameScreen::Embark = self.game_mode {
+                    self.change_level(-1);
+                }
+            }
+            KeyCode::P => {
+                if let GameScreen::Embark = self.game_mode {
+                    self.save_snapshot();
+                }
+            }
+            KeyCode::Equal => {
+                self.zoom_in();
+            }
+            KeyCode::Minus => {
+                self.zoom_out();
+            }
             _ => {
                 warn!("Unhandled keycode: {:?}", keycode);
             }
@@ -300,14 +1555,82 @@ impl GameState {
     pub fn process_inputs(&mut self, events: &mut Vec<UiEvent>) {
         while let Some(event) = events.pop() {
             match event {
+                UiEvent::AbandonLocation(location) => {
+                    if let Some(seed) = location.seed() {
+                        self.sites.remove(&seed);
+                        self.scouted_locations.retain(|l| l.seed() != Some(seed));
+                        self.log_message(format!("Abandoned site {:x}", seed));
+                    }
+                }
+                UiEvent::CraftItem(index) => {
+                    self.craft_item(index);
+                }
+                UiEvent::EditorBrush(brush) => {
+                    self.editor_state.brush = brush;
+                }
+                UiEvent::EditorFrequency(biome, delta) => {
+                    self.editor_state.adjust_frequency(biome, delta);
+                }
+                UiEvent::EditorLoad(path) => match EditorState::load(&path) {
+                    Ok(editor_state) => {
+                        self.log_message(format!("Loaded {}", path.display()));
+                        self.editor_state = editor_state;
+                    }
+                    Err(err) => {
+                        self.log_message(format!("Couldn't load {}: {}", path.display(), err));
+                    }
+                },
+                UiEvent::EditorNew => {
+                    self.editor_state = EditorState::default();
+                }
+                UiEvent::EditorRename(name) => {
+                    self.editor_state.prefab.name = name;
+                }
+                UiEvent::EditorResize(delta) => {
+                    self.editor_state.resize(delta);
+                }
+                UiEvent::EditorSave(target) => match self.editor_state.save(target) {
+                    Ok(path) => {
+                        self.log_message(format!("Saved {}", path.display()));
+                        if target == SaveTarget::Map {
+                            if let Err(err) = mapgen::check_fixed_map(&self.editor_state.prefab) {
+                                self.log_message(format!("It can't be played yet: {}", err));
+                            }
+                        }
+                        // Make new prefabs available to the generator straight away
+                        if target == SaveTarget::Prefab {
+                            self.prefabs = prefab::load_all();
+                        }
+                    }
+                    Err(err) => {
+                        self.log_message(format!("Couldn't save: {}", err));
+                    }
+                },
                 UiEvent::EmbarkLocation(location) => {
                     // Switch to embark/roguelike mode
                     self.next_game_mode = Some(GameScreen::Embark);
                     self.next_location = Some(location);
                 }
+                UiEvent::EmbarkMap(path) => {
+                    self.embark_fixed_map(&path);
+                }
+                UiEvent::EquipItem(index) => {
+                    self.equip_item(index);
+                }
                 UiEvent::KeyPress(key) => {
                     self.process_keypress(key);
                 }
+                UiEvent::TileClicked(pos) => match self.game_mode {
+                    GameScreen::Editor => self.editor_state.paint(pos),
+                    _ => self.start_travel(pos),
+                },
+                UiEvent::MouseWheel(delta) => {
+                    if delta > 0.0 {
+                        self.zoom_in();
+                    } else if delta < 0.0 {
+                        self.zoom_out();
+                    }
+                }
                 UiEvent::Quit => {
                     self.exit_requested = true;
                 }
@@ -335,89 +1658,220 @@ impl GameState {
         //   and establish state
         match self.next_game_mode {
             None => {}
-            Some(screen) => {
-                match screen {
-                    GameScreen::_Title => todo!(),
-                    GameScreen::Idle => {
-                        info!("Going back to idle...");
-                        self.next_game_mode = None;
-                        self.cur_location = Location::AtBase;
+            Some(screen) => match screen {
+                GameScreen::_Title => todo!(),
+                GameScreen::Idle => {
+                    info!("Going back to idle...");
+                    self.next_game_mode = None;
+                    self.store_site();
+                    self.cur_location = Location::AtBase;
 
-                        self.embark_state = EmbarkState::default();
+                    self.bank_cargo();
+                    self.embark_state = EmbarkState::default();
 
-                        self.game_mode = GameScreen::Idle;
-                    }
-                    GameScreen::Embark => {
-                        info!("Beginning embark...");
-                        self.next_game_mode = None;
-                        self.cur_location = self.next_location.unwrap();
-                        self.next_location = None;
-
-                        let x: f32;
-                        let y: f32;
-                        if let Location::Embark(params) = self.cur_location {
-                            x = params.dims.x / 2.0;
-                            y = params.dims.y / 2.0;
-                        } else {
-                            todo!("Resolve disagreement between Gamescreen::Embark and Location::Embark")
-                        }
+                    self.game_mode = GameScreen::Idle;
+                }
+                GameScreen::Embark => {
+                    info!("Beginning embark...");
+                    self.next_game_mode = None;
+                    self.cur_location = self.next_location.unwrap();
+                    self.next_location = None;
 
-                        self.embark_state.player_x = x as u32;
-                        self.embark_state.player_y = y as u32;
+                    let Location::Embark(params) = self.cur_location else {
+                        todo!(
+                            "Resolve disagreement between Gamescreen::Embark and Location::Embark"
+                        )
+                    };
 
-                        self.embark_state.tilemap = Some(self.generate_tilemap());
+                    // Pick up where the last visit left off, if there was one
+                    let site = self.sites.entry(params.seed).or_default();
+                    let mut levels = std::mem::take(&mut site.levels);
+                    self.embark_state.player =
+                        site.spawn.unwrap_or_else(|| mapgen::spawn_point(&params));
 
-                        self.game_mode = GameScreen::Embark;
-                    }
+                    // Gear is fixed for the length of the embark
+                    let stats = self.equipment_stats();
+                    self.embark_state.vision_radius += stats.vision;
+                    self.embark_state.detection += stats.detection;
+                    self.embark_state.attack += stats.attack;
+                    self.embark_state.defense += stats.defense;
+
+                    let level = match levels.remove(&0) {
+                        Some(level) => level,
+                        None => self.generate_level(0),
+                    };
+                    self.embark_state.tilemap = Some(level.tilemap);
+                    self.embark_state.creatures = level.creatures;
+                    self.embark_state.visited_levels = levels;
+
+                    self.game_mode = GameScreen::Embark;
+                }
+                GameScreen::Editor => {
+                    info!("Opening the editor...");
+                    self.next_game_mode = None;
+                    self.editor_state.refresh_saved_files();
+                    self.game_mode = GameScreen::Editor;
                 }
+            },
+        }
+
+        if let GameScreen::Editor = self.game_mode {
+            if let Some(step) = self.embark_state.step.take() {
+                self.editor_state.pan(step);
             }
+            self.mine_requested = false;
         }
 
         if let GameScreen::Embark = self.game_mode {
-            let new_x = if let Some(del_x) = self.embark_state.del_x {
-                self.embark_state.del_x = None;
-                self.embark_state
-                    .player_x
-                    .checked_add_signed(del_x as i32)
-                    .unwrap()
+            let params = if let Location::Embark(params) = self.cur_location {
+                params
             } else {
-                self.embark_state.player_x
+                todo!("Resolve disagreement between Gamescreen::Embark and Location::Embark")
             };
-            let new_y = if let Some(del_y) = self.embark_state.del_y {
-                self.embark_state.del_y = None;
-                self.embark_state
-                    .player_y
-                    .checked_add_signed(del_y as i32)
-                    .unwrap()
+            // Manual input always overrides travel
+            if self.embark_state.step.is_some() || self.mine_requested {
+                self.embark_state.travel_path.clear();
+                self.embark_state.auto_mode = None;
+                self.embark_state.auto_mine_started = false;
             } else {
-                self.embark_state.player_y
+                self.run_auto_mode();
+            }
+
+            let mining_power = self.mining_power();
+            let energy_factor = self.energy_factor();
+            let free_weight = self.embark_state.cargo_capacity - self.cargo_weight();
+            let player = self.embark_state.player;
+            let new_pos = match self.embark_state.step.take() {
+                Some(step) => player + step,
+                None => player,
             };
 
+            // Action points the player spends this frame, letting time pass
+            let mut spent = 0;
+            let mut dug_out: Option<&TileProps> = None;
+            let mut looted = vec![];
+            let mut entered = false;
+            let mut worked = None;
+            let mut detected = vec![];
+            let mut hold_full = false;
+
+            // Moving into a creature attacks it instead
+            let target = self
+                .embark_state
+                .creatures
+                .iter()
+                .position(|creature| creature.pos == new_pos);
+            if let Some(target) = target {
+                spent += Action::Attack.cost();
+                self.player_attack(target);
+            }
+
             if let Some(tilemap) = &mut self.embark_state.tilemap {
-                let tile_index: usize =
-                    (new_y * (tilemap.width as u32) + new_x).try_into().unwrap();
-                if let Tile::Wall = tilemap.tiles[tile_index] {
-                } else {
-                    self.embark_state.player_x = new_x;
-                    self.embark_state.player_y = new_y;
+                // Stepping off the edge of the map goes nowhere
+                let props = tilemap.get(new_pos).map(Tile::props);
+                // An attack takes the place of the step
+                let moving = new_pos != player && target.is_none();
+                if let Some(props) = props.filter(|props| !props.passable) {
+                    // Bumping into soft rock digs at it
+                    if moving && props.diggable {
+                        spent += Action::Dig.cost();
+                        worked = Some(new_pos);
+                        self.resources
+                            .get_mut("energy")
+                            .unwrap()
+                            .add_or_max(-params.dig_cost * energy_factor);
+                        if tilemap.dig(new_pos, mining_power) {
+                            dug_out = Some(props);
+                        }
+                    }
+                } else if let Some(props) = props.filter(|_| moving) {
+                    spent += Action::Move(props.move_cost).cost();
+                    entered = true;
+                    self.embark_state.player = new_pos;
+                    self.resources
+                        .get_mut("energy")
+                        .unwrap()
+                        .add_or_max(-params.move_cost * props.move_cost as f32 * energy_factor);
+                    if let Some(Tile::Cache(items)) = tilemap.tiles.get_mut(new_pos) {
+                        looted = std::mem::take(items);
+                        tilemap.set(new_pos, Tile::Empty);
+                    }
                 }
                 if self.mine_requested {
-                    if let Tile::Resource(resource) = &mut tilemap.tiles[tile_index] {
-                        // TODO: check energy cost of mining!
-                        let key = &resource.key;
-                        info!("Mining {}", key);
-                        let res_handle = self.resources.get_mut(key).unwrap();
-                        res_handle.cur_val += 1.0;
-                        if !self.unlocked_resources.contains(key) {
-                            info!("Unlocking {}", key);
-                            self.unlocked_resources.insert(key.clone());
+                    let here = self.embark_state.player;
+                    let mut depleted = false;
+                    if let Some(Tile::Resource(resource)) = tilemap.tiles.get_mut(here) {
+                        let weight = self.resources.get(&resource.key).unwrap().weight;
+                        let room = if weight > 0.0 {
+                            free_weight / weight
+                        } else {
+                            f32::INFINITY
+                        };
+                        if room <= 0.0 {
+                            hold_full = true;
+                        } else {
+                            spent += Action::Mine.cost();
+                            worked = Some(here);
+                            self.resources
+                                .get_mut("energy")
+                                .unwrap()
+                                .add_or_max(-params.mine_cost * energy_factor);
+                            let yields = resource.work(mining_power);
+                            let mined =
+                                resource.extract((params.mine_yield * yields as f32).min(room));
+                            let key = &resource.key;
+                            info!(
+                                "Mining {} ({:.0}% to next yield), got {:.3}",
+                                key,
+                                100.0 * resource.progress_fraction(),
+                                mined
+                            );
+                            if mined > 0.0 {
+                                *self.embark_state.cargo.entry(key.clone()).or_insert(0.0) += mined;
+                            }
+                            depleted = resource.is_depleted();
                         }
                     }
+                    if depleted {
+                        info!("Deposit exhausted");
+                        tilemap.set(here, Tile::Empty);
+                    }
                     self.mine_requested = false;
                 }
+
+                tilemap
+                    .update_visibility(self.embark_state.player, self.embark_state.vision_radius);
+                detected =
+                    tilemap.detect_hazards(self.embark_state.player, self.embark_state.detection);
             } else {
                 todo!()
             }
+
+            if hold_full {
+                self.log_message("Cargo hold is full!".to_string());
+            }
+            for kind in detected {
+                self.log_message(format!("Scanner warning: {} nearby", kind.stats().name));
+            }
+            if !looted.is_empty() {
+                for item in &looted {
+                    self.log_message(format!("Found {}", item.describe()));
+                }
+                self.embark_state.found_items.extend(looted);
+            }
+            if let Some(props) = dug_out {
+                self.log_message(format!("You dig through the {}", props.name));
+                if let Some((key, amount)) = props.byproduct {
+                    self.add_to_cargo(key, amount);
+                }
+            }
+
+            if spent > 0 {
+                self.apply_tile_hazard();
+                self.trigger_hazards(entered, worked);
+                self.pass_time(spent);
+            }
+            self.check_auto_interrupts();
         }
 
         // Process the idle tick
@@ -428,5 +1882,50 @@ impl GameState {
             // Set last tick time
             self.last_tick = cur_time;
         }
+
+        if let GameScreen::Embark = self.game_mode {
+            self.check_embark_energy();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_with_hazard(hazard_pos: Pos, origin: Pos) -> TileMap {
+        let mut tilemap = TileMap::new(9, 9, Tile::Empty);
+        tilemap
+            .hazards
+            .insert(hazard_pos, Hazard::new(HazardKind::Pit));
+        tilemap.update_visibility(origin, 4);
+        tilemap
+    }
+
+    #[test]
+    fn detect_hazards_needs_a_scanner() {
+        let origin = Pos::new(4, 4);
+        let mut tilemap = map_with_hazard(origin, origin);
+        assert!(tilemap.detect_hazards(origin, 0).is_empty());
+        assert!(!tilemap.hazard(origin).unwrap().discovered);
+    }
+
+    #[test]
+    fn detect_hazards_in_range_once() {
+        let origin = Pos::new(4, 4);
+        let pit = Pos::new(5, 4);
+        let mut tilemap = map_with_hazard(pit, origin);
+        assert_eq!(tilemap.detect_hazards(origin, 1), vec![HazardKind::Pit]);
+        assert!(tilemap.hazard(pit).unwrap().discovered);
+        assert!(tilemap.detect_hazards(origin, 1).is_empty());
+    }
+
+    #[test]
+    fn detect_hazards_skips_out_of_range() {
+        let origin = Pos::new(4, 4);
+        let pit = Pos::new(7, 4);
+        let mut tilemap = map_with_hazard(pit, origin);
+        assert!(tilemap.detect_hazards(origin, 2).is_empty());
+        assert!(!tilemap.hazard(pit).unwrap().discovered);
     }
 }
@@ -0,0 +1,259 @@
+use std::ops::{Add, Sub};
+
+// A tile coordinate. Signed so that offsets can step off the edge of a grid
+//   without wrapping; the grid itself rejects anything out of bounds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Pos {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Pos {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Pos { x, y }
+    }
+
+    pub fn distance_sq(self, other: Pos) -> i32 {
+        let d = self - other;
+        d.x * d.x + d.y * d.y
+    }
+
+    pub fn manhattan(self, other: Pos) -> i32 {
+        let d = self - other;
+        d.x.abs() + d.y.abs()
+    }
+}
+
+impl Add for Pos {
+    type Output = Pos;
+
+    fn add(self, other: Pos) -> Pos {
+        Pos::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Pos {
+    type Output = Pos;
+
+    fn sub(self, other: Pos) -> Pos {
+        Pos::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+// Unit steps up, down, left and right
+pub const ORTHOGONAL: [Pos; 4] = [
+    Pos::new(0, -1),
+    Pos::new(0, 1),
+    Pos::new(-1, 0),
+    Pos::new(1, 0),
+];
+
+// Fixed-size 2D grid stored row by row. Every access is bounds checked and
+//   comes back as an `Option`, so there's no way to index off the edge.
+#[derive(Clone, Debug, Default)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Self
+    where
+        T: Clone,
+    {
+        Grid {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    pub fn from_fn<F>(width: usize, height: usize, mut f: F) -> Self
+    where
+        F: FnMut(Pos) -> T,
+    {
+        let mut cells = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                cells.push(f(Pos::new(x as i32, y as i32)));
+            }
+        }
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn in_bounds(&self, pos: Pos) -> bool {
+        pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < self.width && (pos.y as usize) < self.height
+    }
+
+    fn index(&self, pos: Pos) -> Option<usize> {
+        self.in_bounds(pos)
+            .then(|| pos.y as usize * self.width + pos.x as usize)
+    }
+
+    fn pos(&self, index: usize) -> Pos {
+        Pos::new((index % self.width) as i32, (index / self.width) as i32)
+    }
+
+    pub fn get(&self, pos: Pos) -> Option<&T> {
+        self.index(pos).map(|index| &self.cells[index])
+    }
+
+    pub fn get_mut(&mut self, pos: Pos) -> Option<&mut T> {
+        self.index(pos).map(|index| &mut self.cells[index])
+    }
+
+    // Overwrite the cell at `pos`, returning false if it's off the grid
+    pub fn set(&mut self, pos: Pos, value: T) -> bool {
+        match self.get_mut(pos) {
+            Some(cell) => {
+                *cell = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Orthogonal neighbors of `pos` that lie on the grid
+    pub fn neighbors(&self, pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        ORTHOGONAL
+            .iter()
+            .map(move |step| pos + *step)
+            .filter(|next| self.in_bounds(*next))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Pos, &T)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| (self.pos(index), cell))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.cells.iter_mut()
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        let len = if x < self.width { self.height } else { 0 };
+        self.cells
+            .iter()
+            .skip(x)
+            .step_by(self.width.max(1))
+            .take(len)
+    }
+
+    // Position of the first cell matching `predicate`, scanning row by row
+    pub fn find<P>(&self, predicate: P) -> Option<Pos>
+    where
+        P: Fn(&T) -> bool,
+    {
+        self.cells
+            .iter()
+            .position(predicate)
+            .map(|index| self.pos(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3 wide, 2 tall, with each cell holding its own index
+    fn numbered() -> Grid<usize> {
+        Grid::from_fn(3, 2, |pos| pos.y as usize * 3 + pos.x as usize)
+    }
+
+    #[test]
+    fn get_and_set_reject_out_of_bounds() {
+        let mut grid = numbered();
+        for pos in [
+            Pos::new(-1, 0),
+            Pos::new(0, -1),
+            Pos::new(-1, -1),
+            Pos::new(3, 0),
+            Pos::new(0, 2),
+            Pos::new(3, 2),
+            Pos::new(i32::MIN, i32::MAX),
+        ] {
+            assert_eq!(grid.get(pos), None, "{:?}", pos);
+            assert!(grid.get_mut(pos).is_none(), "{:?}", pos);
+            assert!(!grid.set(pos, 99), "{:?}", pos);
+        }
+        assert!(grid.values().all(|value| *value != 99));
+
+        assert!(grid.set(Pos::new(2, 1), 99));
+        assert_eq!(grid.get(Pos::new(2, 1)), Some(&99));
+        assert_eq!(grid.get(Pos::new(0, 0)), Some(&0));
+    }
+
+    #[test]
+    fn neighbors_stay_on_grid_at_corners() {
+        let grid = numbered();
+        let mut corner: Vec<Pos> = grid.neighbors(Pos::new(0, 0)).collect();
+        corner.sort();
+        assert_eq!(corner, vec![Pos::new(0, 1), Pos::new(1, 0)]);
+
+        let mut corner: Vec<Pos> = grid.neighbors(Pos::new(2, 1)).collect();
+        corner.sort();
+        assert_eq!(corner, vec![Pos::new(1, 1), Pos::new(2, 0)]);
+
+        assert_eq!(grid.neighbors(Pos::new(1, 0)).count(), 3);
+        assert_eq!(grid.neighbors(Pos::new(-5, -5)).count(), 0);
+    }
+
+    #[test]
+    fn rows_and_columns() {
+        let grid = numbered();
+        let rows: Vec<&[usize]> = grid.rows().collect();
+        assert_eq!(rows, vec![&[0, 1, 2][..], &[3, 4, 5][..]]);
+        assert_eq!(grid.column(1).copied().collect::<Vec<_>>(), vec![1, 4]);
+        assert_eq!(grid.column(3).count(), 0);
+        assert_eq!(grid.column(usize::MAX).count(), 0);
+    }
+
+    #[test]
+    fn empty_grids() {
+        let grid: Grid<u8> = Grid::new(0, 3, 0);
+        assert_eq!(grid.rows().count(), 0);
+        assert_eq!(grid.column(0).count(), 0);
+        assert_eq!(grid.get(Pos::new(0, 0)), None);
+        assert_eq!(grid.iter().count(), 0);
+
+        let grid: Grid<u8> = Grid::new(3, 0, 0);
+        assert_eq!(grid.rows().count(), 0);
+        assert_eq!(grid.column(0).count(), 0);
+    }
+
+    #[test]
+    fn find_and_iter_convert_index_to_pos() {
+        let grid = numbered();
+        assert_eq!(grid.find(|value| *value == 0), Some(Pos::new(0, 0)));
+        assert_eq!(grid.find(|value| *value == 2), Some(Pos::new(2, 0)));
+        assert_eq!(grid.find(|value| *value == 3), Some(Pos::new(0, 1)));
+        assert_eq!(grid.find(|value| *value == 5), Some(Pos::new(2, 1)));
+        assert_eq!(grid.find(|value| *value == 6), None);
+        for (pos, value) in grid.iter() {
+            assert_eq!(grid.get(pos), Some(value));
+        }
+    }
+}
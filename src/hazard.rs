@@ -0,0 +1,93 @@
+// Hidden dangers laid over an embark map
+//   Hazards sit on top of the terrain rather than replacing it, and stay
+//   invisible until the player either blunders into one or picks it up
+//   with survey equipment. Once discovered they're drawn on the map.
+
+use macroquad::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HazardKind {
+    // Poisonous air that hurts every turn spent in it
+    GasPocket,
+    // Unstable rock that comes down when it's mined or dug at
+    CaveIn,
+    // Drop hidden under loose floor, hurting the player when they step onto it
+    Pit,
+    // Contaminated ground that drains the batteries every turn spent in it
+    Radiation,
+}
+
+// When a hazard goes off
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trigger {
+    // Every turn the player ends on it
+    Stand,
+    // Each time the player steps onto it
+    Enter,
+    // When the player mines or digs at it
+    Mine,
+}
+
+pub struct HazardStats {
+    pub name: &'static str,
+    pub color: Color,
+    pub trigger: Trigger,
+    // Told to the player when it goes off
+    pub message: &'static str,
+    pub damage: f32,
+    pub energy_drain: f32,
+}
+
+impl HazardKind {
+    pub fn stats(&self) -> HazardStats {
+        match self {
+            HazardKind::GasPocket => HazardStats {
+                name: "gas pocket",
+                color: LIME,
+                trigger: Trigger::Stand,
+                message: "You breathe in toxic gas",
+                damage: 2.0,
+                energy_drain: 0.0,
+            },
+            HazardKind::CaveIn => HazardStats {
+                name: "unstable rock",
+                color: DARKGRAY,
+                trigger: Trigger::Mine,
+                message: "The rock caves in on you",
+                damage: 6.0,
+                energy_drain: 0.0,
+            },
+            HazardKind::Pit => HazardStats {
+                name: "pit",
+                color: DARKPURPLE,
+                trigger: Trigger::Enter,
+                message: "You fall into a hidden pit",
+                damage: 4.0,
+                energy_drain: 0.0,
+            },
+            HazardKind::Radiation => HazardStats {
+                name: "radiation",
+                color: GREEN,
+                trigger: Trigger::Stand,
+                message: "Radiation saps your batteries",
+                damage: 0.0,
+                energy_drain: 2.0,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    pub discovered: bool,
+}
+
+impl Hazard {
+    pub fn new(kind: HazardKind) -> Self {
+        Hazard {
+            kind,
+            discovered: false,
+        }
+    }
+}
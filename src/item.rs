@@ -0,0 +1,234 @@
+use std::ops::Add;
+
+// Where an item is worn or carried; one item per slot can be equipped
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Slot {
+    Tool,
+    Armor,
+    Lamp,
+    Battery,
+    Scanner,
+}
+
+impl Slot {
+    pub const ALL: [Slot; 5] = [
+        Slot::Tool,
+        Slot::Armor,
+        Slot::Lamp,
+        Slot::Battery,
+        Slot::Scanner,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Slot::Tool => "tool",
+            Slot::Armor => "armor",
+            Slot::Lamp => "lamp",
+            Slot::Battery => "battery",
+            Slot::Scanner => "scanner",
+        }
+    }
+}
+
+// Bonuses an item gives while equipped. Equipped items simply add up.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ItemStats {
+    pub mining_power: f32,
+    pub vision: u32,
+    // Fraction shaved off every energy cost on an embark
+    pub energy_efficiency: f32,
+    pub attack: f32,
+    pub defense: f32,
+    // Range, in tiles, at which hidden hazards in view are picked up
+    pub detection: u32,
+}
+
+impl Add for ItemStats {
+    type Output = ItemStats;
+
+    fn add(self, other: ItemStats) -> ItemStats {
+        ItemStats {
+            mining_power: self.mining_power + other.mining_power,
+            vision: self.vision + other.vision,
+            energy_efficiency: self.energy_efficiency + other.energy_efficiency,
+            attack: self.attack + other.attack,
+            defense: self.defense + other.defense,
+            detection: self.detection + other.detection,
+        }
+    }
+}
+
+impl ItemStats {
+    // Short summary of the nonzero bonuses, e.g. "+1.5 mining, +2 vision"
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+        if self.mining_power != 0.0 {
+            parts.push(format!("{:+.1} mining", self.mining_power));
+        }
+        if self.vision != 0 {
+            parts.push(format!("+{} vision", self.vision));
+        }
+        if self.energy_efficiency != 0.0 {
+            parts.push(format!(
+                "{:+.0}% efficiency",
+                100.0 * self.energy_efficiency
+            ));
+        }
+        if self.attack != 0.0 {
+            parts.push(format!("{:+.0} attack", self.attack));
+        }
+        if self.defense != 0.0 {
+            parts.push(format!("{:+.0} defense", self.defense));
+        }
+        if self.detection != 0 {
+            parts.push(format!("+{} detection", self.detection));
+        }
+        parts.join(", ")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    pub name: String,
+    pub slot: Slot,
+    pub stats: ItemStats,
+}
+
+impl Item {
+    pub fn describe(&self) -> String {
+        format!("{} ({})", self.name, self.stats.describe())
+    }
+}
+
+// Something the workshop can build, and the resources it takes
+pub struct Recipe {
+    pub name: &'static str,
+    pub slot: Slot,
+    pub stats: ItemStats,
+    pub cost: &'static [(&'static str, f32)],
+}
+
+impl Recipe {
+    pub fn item(&self) -> Item {
+        Item {
+            name: self.name.to_string(),
+            slot: self.slot,
+            stats: self.stats,
+        }
+    }
+}
+
+const NO_STATS: ItemStats = ItemStats {
+    mining_power: 0.0,
+    vision: 0,
+    energy_efficiency: 0.0,
+    attack: 0.0,
+    defense: 0.0,
+    detection: 0,
+};
+
+// What every new game starts with equipped
+pub fn starting_equipment() -> Vec<Item> {
+    vec![Item {
+        name: "Hand drill".to_string(),
+        slot: Slot::Tool,
+        stats: ItemStats {
+            mining_power: 1.0,
+            ..NO_STATS
+        },
+    }]
+}
+
+pub const RECIPES: [Recipe; 10] = [
+    Recipe {
+        name: "Iron drill",
+        slot: Slot::Tool,
+        stats: ItemStats {
+            mining_power: 1.5,
+            ..NO_STATS
+        },
+        cost: &[("iron_ore", 10.0)],
+    },
+    Recipe {
+        name: "Heavy drill",
+        slot: Slot::Tool,
+        stats: ItemStats {
+            mining_power: 2.5,
+            attack: 1.0,
+            ..NO_STATS
+        },
+        cost: &[("iron_ore", 25.0), ("bauxite", 10.0)],
+    },
+    Recipe {
+        name: "Padded suit",
+        slot: Slot::Armor,
+        stats: ItemStats {
+            defense: 1.0,
+            ..NO_STATS
+        },
+        cost: &[("sandstone", 10.0)],
+    },
+    Recipe {
+        name: "Plated suit",
+        slot: Slot::Armor,
+        stats: ItemStats {
+            defense: 3.0,
+            ..NO_STATS
+        },
+        cost: &[("iron_ore", 20.0), ("sandstone", 10.0)],
+    },
+    Recipe {
+        name: "Oil lamp",
+        slot: Slot::Lamp,
+        stats: ItemStats {
+            vision: 2,
+            ..NO_STATS
+        },
+        cost: &[("bauxite", 5.0)],
+    },
+    Recipe {
+        name: "Arc lamp",
+        slot: Slot::Lamp,
+        stats: ItemStats {
+            vision: 4,
+            ..NO_STATS
+        },
+        cost: &[("iron_ore", 10.0), ("bauxite", 10.0)],
+    },
+    Recipe {
+        name: "Cell pack",
+        slot: Slot::Battery,
+        stats: ItemStats {
+            energy_efficiency: 0.15,
+            ..NO_STATS
+        },
+        cost: &[("bauxite", 15.0)],
+    },
+    Recipe {
+        name: "Heavy cell",
+        slot: Slot::Battery,
+        stats: ItemStats {
+            energy_efficiency: 0.3,
+            ..NO_STATS
+        },
+        cost: &[("iron_ore", 10.0), ("bauxite", 25.0)],
+    },
+    Recipe {
+        name: "Gas sniffer",
+        slot: Slot::Scanner,
+        stats: ItemStats {
+            detection: 2,
+            ..NO_STATS
+        },
+        cost: &[("iron_ore", 5.0), ("bauxite", 5.0)],
+    },
+    Recipe {
+        name: "Survey scanner",
+        slot: Slot::Scanner,
+        stats: ItemStats {
+            detection: 5,
+            ..NO_STATS
+        },
+        cost: &[("iron_ore", 15.0), ("bauxite", 15.0), ("sandstone", 10.0)],
+    },
+];
@@ -0,0 +1,175 @@
+use macroquad::rand::RandGenerator;
+
+use crate::game::Biome;
+use crate::item::Item;
+use crate::item::ItemStats;
+use crate::item::Slot;
+use crate::item::RECIPES;
+
+// Items in a single cache
+const MIN_CACHE_ITEMS: u32 = 1;
+const MAX_CACHE_ITEMS: u32 = 2;
+
+// Depth from which caches can hold the better version of an item
+const ADVANCED_ITEM_DEPTH: u32 = 2;
+
+// Odds out of 100 of each affix slot being filled, going up with depth
+const AFFIX_CHANCE: u32 = 35;
+const AFFIX_CHANCE_PER_DEPTH: u32 = 15;
+// How much stronger affixes get per level of depth
+const AFFIX_DEPTH_SCALE: f32 = 0.5;
+
+// A random modifier on a found item. The first affix an item rolls names
+//   it by `prefix`, the second by `suffix`.
+struct Affix {
+    prefix: &'static str,
+    suffix: &'static str,
+    stats: ItemStats,
+}
+
+const fn affix_stats(
+    mining_power: f32,
+    vision: u32,
+    energy_efficiency: f32,
+    attack: f32,
+    defense: f32,
+    detection: u32,
+) -> ItemStats {
+    ItemStats {
+        mining_power,
+        vision,
+        energy_efficiency,
+        attack,
+        defense,
+        detection,
+    }
+}
+
+const AFFIXES: [Affix; 6] = [
+    Affix {
+        prefix: "Keen",
+        suffix: "of Cutting",
+        stats: affix_stats(0.5, 0, 0.0, 0.0, 0.0, 0),
+    },
+    Affix {
+        prefix: "Bright",
+        suffix: "of Sight",
+        stats: affix_stats(0.0, 1, 0.0, 0.0, 0.0, 0),
+    },
+    Affix {
+        prefix: "Thrifty",
+        suffix: "of Conservation",
+        stats: affix_stats(0.0, 0, 0.05, 0.0, 0.0, 0),
+    },
+    Affix {
+        prefix: "Jagged",
+        suffix: "of Wounding",
+        stats: affix_stats(0.0, 0, 0.0, 1.0, 0.0, 0),
+    },
+    Affix {
+        prefix: "Sturdy",
+        suffix: "of Warding",
+        stats: affix_stats(0.0, 0, 0.0, 0.0, 1.0, 0),
+    },
+    Affix {
+        prefix: "Attuned",
+        suffix: "of Warning",
+        stats: affix_stats(0.0, 0, 0.0, 0.0, 0.0, 1),
+    },
+];
+
+// Relative odds of finding gear for each slot; each biome favors what's
+//   most useful for surviving it
+fn slot_weights(biome: Biome) -> [(Slot, u32); 5] {
+    match biome {
+        Biome::Caverns => [
+            (Slot::Tool, 3),
+            (Slot::Armor, 2),
+            (Slot::Lamp, 2),
+            (Slot::Battery, 2),
+            (Slot::Scanner, 1),
+        ],
+        Biome::Flooded => [
+            (Slot::Tool, 1),
+            (Slot::Armor, 1),
+            (Slot::Lamp, 3),
+            (Slot::Battery, 4),
+            (Slot::Scanner, 1),
+        ],
+        Biome::Volcanic => [
+            (Slot::Tool, 3),
+            (Slot::Armor, 4),
+            (Slot::Lamp, 1),
+            (Slot::Battery, 1),
+            (Slot::Scanner, 2),
+        ],
+    }
+}
+
+pub fn roll_cache(rng: &RandGenerator, biome: Biome, depth: u32) -> Vec<Item> {
+    let count = rng.gen_range(MIN_CACHE_ITEMS, MAX_CACHE_ITEMS + 1);
+    (0..count).map(|_| roll_item(rng, biome, depth)).collect()
+}
+
+// A workshop item picked by the biome's slot weights, with up to two random
+//   affixes. Deeper levels roll better bases and more, stronger affixes.
+pub fn roll_item(rng: &RandGenerator, biome: Biome, depth: u32) -> Item {
+    let weights = slot_weights(biome);
+    let total: u32 = weights.iter().map(|(_, weight)| weight).sum();
+    let mut roll = rng.gen_range(0, total);
+    let slot = weights
+        .iter()
+        .find(|(_, weight)| {
+            if roll < *weight {
+                return true;
+            }
+            roll -= weight;
+            false
+        })
+        .map_or(Slot::Tool, |(slot, _)| *slot);
+
+    // Recipes for a slot are listed from basic to advanced
+    let bases: Vec<_> = RECIPES
+        .iter()
+        .filter(|recipe| recipe.slot == slot)
+        .collect();
+    let tiers = if depth >= ADVANCED_ITEM_DEPTH {
+        bases.len()
+    } else {
+        1
+    };
+    let mut item = bases[rng.gen_range(0, tiers)].item();
+
+    let chance = AFFIX_CHANCE + AFFIX_CHANCE_PER_DEPTH * depth;
+    let scale = 1.0 + AFFIX_DEPTH_SCALE * depth as f32;
+    let mut prefix = None;
+    let mut suffix = None;
+    for _ in 0..2 {
+        if rng.gen_range(0, 100) >= chance {
+            continue;
+        }
+        let affix = &AFFIXES[rng.gen_range(0, AFFIXES.len())];
+        let stats = affix.stats;
+        item.stats = item.stats
+            + ItemStats {
+                mining_power: stats.mining_power * scale,
+                vision: stats.vision * scale.floor() as u32,
+                energy_efficiency: stats.energy_efficiency * scale,
+                attack: (stats.attack * scale).round(),
+                defense: (stats.defense * scale).round(),
+                detection: stats.detection * scale.floor() as u32,
+            };
+        if prefix.is_none() {
+            prefix = Some(affix.prefix);
+        } else {
+            suffix = Some(affix.suffix);
+        }
+    }
+    if let Some(prefix) = prefix {
+        item.name = format!("{} {}", prefix, item.name.to_lowercase());
+    }
+    if let Some(suffix) = suffix {
+        item.name = format!("{} {}", item.name, suffix);
+    }
+    item
+}
@@ -1,5 +1,18 @@
+pub mod ascii;
+pub mod creature;
+pub mod editor;
+pub mod export;
+pub mod fov;
 pub mod game;
+pub mod grid;
+pub mod hazard;
+pub mod item;
+pub mod loot;
+pub mod mapgen;
+pub mod pathfinding;
+pub mod prefab;
 pub mod render;
+pub mod turn;
 
 use game::GameState;
 use game::UiEvent;
@@ -8,10 +21,29 @@ use render::render_frame;
 
 use macroquad::prelude::*;
 
-#[macroquad::main("Unnamed Incremental Roguelike")]
-async fn main() {
+fn main() {
+    // Batch tools run headless, without ever opening the game window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some((command, rest)) = args.split_first() {
+        if command == export::EXPORT_COMMAND {
+            if let Err(err) = export::run_command(rest) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+    }
+
+    macroquad::Window::new("Unnamed Incremental Roguelike", run_game());
+}
+
+async fn run_game() {
     info!("Starting preamble");
 
+    // Scouted sites are rolled from the global generator, so seed it per
+    //   session or every run finds the same ones
+    rand::srand(miniquad::date::now() as u64);
+
     let mut state: GameState = GameState::default();
 
     let mut platform_event_queue: Vec<UiEvent> = vec![];
@@ -42,7 +74,7 @@ async fn main() {
 
         // Check if screen size has changed every .1 seconds
         // TODO: Check and enforce framerate
-        if frame_counter % 6 == 0usize {
+        if frame_counter.is_multiple_of(6) {
             platform_event_queue.push(UiEvent::Resize(screen_width(), screen_height()));
         }
 
@@ -53,6 +85,9 @@ async fn main() {
         if is_key_pressed(KeyCode::I) {
             platform_event_queue.push(UiEvent::KeyPress(KeyCode::I));
         }
+        if is_key_pressed(KeyCode::R) {
+            platform_event_queue.push(UiEvent::KeyPress(KeyCode::R));
+        }
         if is_key_pressed(KeyCode::Right) {
             platform_event_queue.push(UiEvent::KeyPress(KeyCode::Right));
         }
@@ -68,6 +103,31 @@ async fn main() {
         if is_key_pressed(KeyCode::Space) {
             platform_event_queue.push(UiEvent::KeyPress(KeyCode::Space));
         }
+        if is_key_pressed(KeyCode::X) {
+            platform_event_queue.push(UiEvent::KeyPress(KeyCode::X));
+        }
+        if is_key_pressed(KeyCode::M) {
+            platform_event_queue.push(UiEvent::KeyPress(KeyCode::M));
+        }
+        if is_key_pressed(KeyCode::Period) {
+            platform_event_queue.push(UiEvent::KeyPress(KeyCode::Period));
+        }
+        if is_key_pressed(KeyCode::Comma) {
+            platform_event_queue.push(UiEvent::KeyPress(KeyCode::Comma));
+        }
+        if is_key_pressed(KeyCode::P) {
+            platform_event_queue.push(UiEvent::KeyPress(KeyCode::P));
+        }
+        if is_key_pressed(KeyCode::Equal) {
+            platform_event_queue.push(UiEvent::KeyPress(KeyCode::Equal));
+        }
+        if is_key_pressed(KeyCode::Minus) {
+            platform_event_queue.push(UiEvent::KeyPress(KeyCode::Minus));
+        }
+        let (_wheel_x, wheel_y) = mouse_wheel();
+        if wheel_y != 0.0 {
+            platform_event_queue.push(UiEvent::MouseWheel(wheel_y));
+        }
 
         state.process_inputs(&mut platform_event_queue);
         if state.exit_requested {
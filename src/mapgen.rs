@@ -0,0 +1,776 @@
+use macroquad::prelude::*;
+use macroquad::rand::RandGenerator;
+
+use crate::creature::Creature;
+use crate::creature::CreatureKind;
+use crate::game::Biome;
+use crate::game::EmbarkParams;
+use crate::game::Level;
+use crate::game::Tile;
+use crate::game::TileMap;
+use crate::game::TileResource;
+use crate::grid::Grid;
+use crate::grid::Pos;
+use crate::hazard::Hazard;
+use crate::hazard::HazardKind;
+use crate::loot;
+use crate::pathfinding;
+use crate::prefab::Prefab;
+use crate::prefab::PrefabCell;
+
+// Odds that any given interior tile rolls as rock or as a deposit
+const ROCK_CHANCE: f32 = 0.08;
+const DEPOSIT_CHANCE: f32 = 0.01;
+// Odds of the rarer terrain; lava only appears below the surface
+const RUBBLE_CHANCE: f32 = 0.02;
+const WATER_CHANCE: f32 = 0.015;
+const LAVA_CHANCE_PER_DEPTH: f32 = 0.004;
+
+// Cost of tunnelling through a tile when connecting a walled off landmark,
+//   relative to crossing open floor
+const TUNNEL_COST: u32 = 4;
+// Times a level is rolled before giving up on prefabs, and the salt mixed
+//   into the seed for each reroll
+const GENERATION_ATTEMPTS: usize = 5;
+const REROLL_SEED_SALT: usize = 0x2545_f491;
+
+// Flooded sites roll this much more water; volcanic ones roll lava as if
+//   a level deeper than they are
+const FLOODED_WATER_SCALE: f32 = 3.0;
+
+// Prefabs to try stamping onto each level, and how many spots to try for each
+const PREFABS_PER_LEVEL: usize = 3;
+const PREFAB_PLACEMENT_TRIES: usize = 20;
+// Keeps prefab placement independent of the terrain roll
+const PREFAB_SEED_SALT: u64 = 0xfab_1ab5;
+
+// Loot caches scattered over each level, and how close to the landing spot
+//   they may be
+const CACHE_COUNT: usize = 2;
+const CACHE_MIN_SPAWN_DISTANCE: i32 = 8;
+// Keeps cache placement and contents independent of the terrain roll
+const LOOT_SEED_SALT: u64 = 0x10_07ca_c4e5;
+
+// Hazards hidden around each level, and how close to the landing spot
+//   they may be
+const HAZARD_COUNT: usize = 6;
+const HAZARD_MIN_SPAWN_DISTANCE: i32 = 8;
+const HAZARD_PLACEMENT_TRIES: usize = 100;
+// Radius of the patch of floor covered by a gas pocket or radiation zone
+const GAS_RADIUS: i32 = 1;
+const RADIATION_RADIUS: i32 = 3;
+// Radiation only leaks into the levels below the surface
+const RADIATION_MIN_DEPTH: u32 = 1;
+// Keeps hazard placement independent of the terrain roll
+const HAZARD_SEED_SALT: u64 = 0x4a2a_7d00;
+
+// Number of landing pads the player can extract from
+const EXTRACTION_POINTS: usize = 2;
+
+// Creatures roaming each site, and how close to the landing spot they may start
+const CREATURE_COUNT: usize = 12;
+const CREATURE_MIN_SPAWN_DISTANCE: i32 = 10;
+// Keeps the creature roll independent of the terrain roll for the same seed
+const CREATURE_SEED_SALT: u64 = 0x5eed_c0de;
+// Mixed in once per level of depth, so each level of a site rolls differently
+const LEVEL_SEED_SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+// Per level of depth: extra deposit odds and richness, and extra creatures,
+//   caches and hazards
+const DEPTH_DEPOSIT_SCALE: f32 = 0.5;
+const DEPTH_EXTRA_CREATURES: usize = 4;
+const DEPTH_EXTRA_CACHES: usize = 1;
+const DEPTH_EXTRA_HAZARDS: usize = 3;
+
+struct DepositKind {
+    key: &'static str,
+    color: Color,
+    min_amount: f32,
+    max_amount: f32,
+    hardness: f32,
+}
+
+const DEPOSIT_KINDS: [DepositKind; 3] = [
+    DepositKind {
+        key: "iron_ore",
+        color: BLUE,
+        min_amount: 5.0,
+        max_amount: 15.0,
+        hardness: 3.0,
+    },
+    DepositKind {
+        key: "bauxite",
+        color: BROWN,
+        min_amount: 5.0,
+        max_amount: 15.0,
+        hardness: 2.0,
+    },
+    DepositKind {
+        key: "sandstone",
+        color: BEIGE,
+        min_amount: 10.0,
+        max_amount: 20.0,
+        hardness: 1.0,
+    },
+];
+
+// Build one level of an embark site. Generation is driven entirely by
+//   `params`, `depth` and the available prefabs, so the same level always
+//   comes out the same. A level with a landmark that can't be connected to
+//   the spawn (prefab bedrock can seal one in) is rerolled with a salted
+//   seed, falling back to a level without prefabs, which always connects.
+pub fn generate_level(params: &EmbarkParams, depth: u32, prefabs: &[Prefab]) -> Level {
+    for attempt in 0..GENERATION_ATTEMPTS {
+        let attempt_params = EmbarkParams {
+            seed: params.seed ^ attempt.wrapping_mul(REROLL_SEED_SALT),
+            ..*params
+        };
+        if let Some(level) = try_generate_level(&attempt_params, depth, prefabs) {
+            return level;
+        }
+        warn!(
+            "Rerolling depth {} of seed {}: a landmark can't be reached",
+            depth, params.seed
+        );
+    }
+    try_generate_level(params, depth, &[]).expect("levels without prefabs always connect")
+}
+
+fn try_generate_level(params: &EmbarkParams, depth: u32, prefabs: &[Prefab]) -> Option<Level> {
+    let mut tilemap = generate_tilemap(params, depth);
+    let (placed, footprints) = stamp_prefabs(params, depth, prefabs, &mut tilemap);
+    place_caches(params, depth, &footprints, &mut tilemap);
+    if !ensure_connected(&mut tilemap, spawn_point(params)) {
+        return None;
+    }
+    place_hazards(params, depth, &mut tilemap);
+    let creatures = generate_creatures(params, depth, &tilemap, placed);
+    Some(Level { tilemap, creatures })
+}
+
+fn level_seed(params: &EmbarkParams, depth: u32) -> u64 {
+    params.seed as u64 ^ (depth as u64).wrapping_mul(LEVEL_SEED_SALT)
+}
+
+fn depth_scale(depth: u32) -> f32 {
+    1.0 + DEPTH_DEPOSIT_SCALE * depth as f32
+}
+
+pub fn generate_tilemap(params: &EmbarkParams, depth: u32) -> TileMap {
+    let rng = RandGenerator::new();
+    rng.srand(level_seed(params, depth));
+    let deposit_chance = DEPOSIT_CHANCE * depth_scale(depth);
+    let (water_chance, lava_chance) = match params.biome {
+        Biome::Caverns => (WATER_CHANCE, LAVA_CHANCE_PER_DEPTH * depth as f32),
+        Biome::Flooded => (
+            WATER_CHANCE * FLOODED_WATER_SCALE,
+            LAVA_CHANCE_PER_DEPTH * depth as f32,
+        ),
+        Biome::Volcanic => (WATER_CHANCE, LAVA_CHANCE_PER_DEPTH * (depth + 1) as f32),
+    };
+
+    // Cumulative odds for each terrain roll, checked in order
+    let terrain_odds = [
+        (ROCK_CHANCE, Tile::Wall),
+        (RUBBLE_CHANCE, Tile::Rubble),
+        (water_chance, Tile::Water),
+        (lava_chance, Tile::Lava),
+    ];
+
+    let (width, height) = params.dims;
+    let tiles = Grid::from_fn(width, height, |pos| {
+        let x = pos.x as usize;
+        let y = pos.y as usize;
+        if y == 0 || y == height - 1 || x == 0 || x == width - 1 {
+            return Tile::Bedrock;
+        }
+
+        let mut roll = rng.gen_range(0.0f32, 1.0);
+        let terrain = terrain_odds.iter().find(|(chance, _)| {
+            roll -= chance;
+            roll < 0.0
+        });
+        if let Some((_, tile)) = terrain {
+            tile.clone()
+        } else if roll < deposit_chance {
+            roll_deposit(&rng, depth)
+        } else {
+            Tile::Empty
+        }
+    });
+    let mut tilemap = TileMap::from_tiles(tiles);
+
+    // Never drop the player into solid rock. Below the surface, the player
+    //   arrives by the stairs up.
+    let spawn = spawn_point(params);
+    let spawn_tile = if depth == 0 {
+        Tile::Empty
+    } else {
+        Tile::StairsUp
+    };
+    tilemap.set(spawn, spawn_tile);
+
+    // Rerolls while the tile is already the spawn or another landmark, so
+    //   pads and stairs never replace each other
+    let random_interior_tile = |tilemap: &TileMap| loop {
+        let pos = random_interior_pos(&rng, params.dims);
+        let taken = tilemap.get(pos).is_some_and(is_landmark);
+        if pos != spawn && !taken {
+            break pos;
+        }
+    };
+
+    // Landing pads only make sense on the surface
+    if depth == 0 {
+        for _ in 0..EXTRACTION_POINTS {
+            let pos = random_interior_tile(&tilemap);
+            tilemap.set(pos, Tile::Extraction);
+        }
+    }
+
+    if depth + 1 < params.max_depth {
+        let pos = random_interior_tile(&tilemap);
+        tilemap.set(pos, Tile::StairsDown);
+    }
+
+    tilemap
+}
+
+// What keeps a painted map from being played as a site: it needs a spawn
+//   to land on and a pad to leave from
+pub fn check_fixed_map(map: &Prefab) -> Result<(), String> {
+    let has = |wanted: fn(&PrefabCell) -> bool| {
+        map.cells
+            .iter()
+            .any(|(_, cell)| cell.as_ref().is_some_and(wanted))
+    };
+    if !has(|cell| matches!(cell, PrefabCell::Spawn)) {
+        return Err("it has no spawn".to_string());
+    }
+    if !has(|cell| matches!(cell, PrefabCell::Tile(Tile::Extraction))) {
+        return Err("it has no landing pad".to_string());
+    }
+    Ok(())
+}
+
+// Build the top level of a site from a fixed map painted in the editor,
+//   rolling its deposits and caches like generated ones. Unpainted cells are
+//   solid rock, so walled off landmarks are tunneled to as on generated
+//   levels. Returns the level and where the player lands: the map's spawn,
+//   or its first open tile if it hasn't got one.
+pub fn fixed_level(params: &EmbarkParams, map: &Prefab) -> Result<(Level, Pos), String> {
+    let rng = RandGenerator::new();
+    rng.srand(level_seed(params, 0));
+    let mut spawn = None;
+    let mut creatures = vec![];
+    let tiles = Grid::from_fn(map.cells.width(), map.cells.height(), |pos| {
+        match map.cells.get(pos).and_then(Option::as_ref) {
+            None => Tile::Wall,
+            Some(PrefabCell::Tile(Tile::Cache(_))) => {
+                Tile::Cache(loot::roll_cache(&rng, params.biome, 0))
+            }
+            Some(PrefabCell::Tile(tile)) => tile.clone(),
+            Some(PrefabCell::Deposit(key)) => DEPOSIT_KINDS
+                .iter()
+                .find(|kind| kind.key == key)
+                .map_or(Tile::Empty, |kind| deposit(&rng, kind, 0)),
+            Some(PrefabCell::Creature(kind)) => {
+                creatures.push(Creature::new(*kind, pos));
+                Tile::Empty
+            }
+            Some(PrefabCell::Spawn) => {
+                spawn = Some(pos);
+                Tile::Empty
+            }
+        }
+    });
+    let mut tilemap = TileMap::from_tiles(tiles);
+    let spawn = spawn
+        .or_else(|| tilemap.tiles.find(is_walkable))
+        .ok_or("it has no open floor to land on")?;
+    if !ensure_connected(&mut tilemap, spawn) {
+        return Err("part of it is sealed off by bedrock".to_string());
+    }
+    Ok((Level { tilemap, creatures }, spawn))
+}
+
+// Stamp a handful of prefabs, picked by their frequency in this biome, over
+//   the generated terrain. Prefabs have to fit inside the bedrock border,
+//   may not cover landmarks or each other, and are held to the same distance
+//   from the landing spot as any other creature for the creatures they
+//   bring. Returns those creatures, and the area each prefab covers.
+fn stamp_prefabs(
+    params: &EmbarkParams,
+    depth: u32,
+    prefabs: &[Prefab],
+    tilemap: &mut TileMap,
+) -> (Vec<Creature>, Vec<Rect>) {
+    let rng = RandGenerator::new();
+    rng.srand(level_seed(params, depth) ^ PREFAB_SEED_SALT);
+    let total: u32 = prefabs.iter().map(|p| p.frequency(params.biome)).sum();
+    if total == 0 {
+        return (vec![], vec![]);
+    }
+
+    let spawn = spawn_point(params);
+    let mut footprints: Vec<Rect> = vec![];
+    let mut creatures = vec![];
+    for _ in 0..PREFABS_PER_LEVEL {
+        let mut roll = rng.gen_range(0, total);
+        let Some(prefab) = prefabs.iter().find(|p| {
+            let frequency = p.frequency(params.biome);
+            if roll < frequency {
+                return true;
+            }
+            roll -= frequency;
+            false
+        }) else {
+            continue;
+        };
+
+        let size = Pos::new(prefab.cells.width() as i32, prefab.cells.height() as i32);
+        let max_x = tilemap.width() as i32 - 1 - size.x;
+        let max_y = tilemap.height() as i32 - 1 - size.y;
+        if max_x < 1 || max_y < 1 {
+            continue;
+        }
+        for _ in 0..PREFAB_PLACEMENT_TRIES {
+            let corner = Pos::new(rng.gen_range(1, max_x + 1), rng.gen_range(1, max_y + 1));
+            let footprint = Rect::new(
+                corner.x as f32,
+                corner.y as f32,
+                size.x as f32,
+                size.y as f32,
+            );
+            let overlaps = footprints.iter().any(|other| other.overlaps(&footprint));
+            let covers_landmark = prefab.cells.iter().any(|(offset, _)| {
+                let pos = corner + offset;
+                pos == spawn
+                    || matches!(
+                        tilemap.get(pos),
+                        Some(Tile::Extraction | Tile::StairsDown | Tile::StairsUp)
+                    )
+            });
+            let crowds_spawn = prefab.cells.iter().any(|(offset, cell)| {
+                matches!(cell, Some(PrefabCell::Creature(_)))
+                    && (corner + offset).distance_sq(spawn) < CREATURE_MIN_SPAWN_DISTANCE.pow(2)
+            });
+            if overlaps || covers_landmark || crowds_spawn {
+                continue;
+            }
+
+            for (offset, cell) in prefab.cells.iter() {
+                let pos = corner + offset;
+                match cell {
+                    None => {}
+                    Some(PrefabCell::Tile(Tile::Cache(_))) => {
+                        let items = loot::roll_cache(&rng, params.biome, depth);
+                        tilemap.set(pos, Tile::Cache(items));
+                    }
+                    Some(PrefabCell::Tile(tile)) => {
+                        tilemap.set(pos, tile.clone());
+                    }
+                    Some(PrefabCell::Deposit(key)) => {
+                        if let Some(kind) = DEPOSIT_KINDS.iter().find(|kind| kind.key == key) {
+                            tilemap.set(pos, deposit(&rng, kind, depth));
+                        }
+                    }
+                    Some(PrefabCell::Creature(kind)) => {
+                        tilemap.set(pos, Tile::Empty);
+                        creatures.push(Creature::new(*kind, pos));
+                    }
+                    Some(PrefabCell::Spawn) => {
+                        tilemap.set(pos, Tile::Empty);
+                    }
+                }
+            }
+            info!("Placed prefab {} at {:?}", prefab.name, corner);
+            footprints.push(footprint);
+            break;
+        }
+    }
+    (creatures, footprints)
+}
+
+// Drop loot caches onto open floor away from the landing spot, leaving the
+//   `footprints` of stamped prefabs as they were laid out. What's in them
+//   depends on the biome and gets better with depth.
+fn place_caches(params: &EmbarkParams, depth: u32, footprints: &[Rect], tilemap: &mut TileMap) {
+    let rng = RandGenerator::new();
+    rng.srand(level_seed(params, depth) ^ LOOT_SEED_SALT);
+    let count = CACHE_COUNT + DEPTH_EXTRA_CACHES * depth as usize;
+    let spawn = spawn_point(params);
+    let dims = (tilemap.width(), tilemap.height());
+
+    let mut placed = 0;
+    // Bounded so a cramped map can't stall generation
+    for _ in 0..count * 100 {
+        if placed == count {
+            break;
+        }
+        let pos = random_interior_pos(&rng, dims);
+        let open = matches!(tilemap.get(pos), Some(Tile::Empty));
+        let far = pos.distance_sq(spawn) >= CACHE_MIN_SPAWN_DISTANCE.pow(2);
+        let point = vec2(pos.x as f32, pos.y as f32);
+        let outside = !footprints.iter().any(|footprint| footprint.contains(point));
+        if open && far && outside {
+            tilemap.set(
+                pos,
+                Tile::Cache(loot::roll_cache(&rng, params.biome, depth)),
+            );
+            placed += 1;
+        }
+    }
+}
+
+// Relative odds of each hazard turning up; each biome has its own dangers
+fn hazard_weights(biome: Biome, depth: u32) -> [(HazardKind, u32); 4] {
+    let radiation = if depth >= RADIATION_MIN_DEPTH { 1 } else { 0 };
+    match biome {
+        Biome::Caverns => [
+            (HazardKind::GasPocket, 2),
+            (HazardKind::CaveIn, 3),
+            (HazardKind::Pit, 3),
+            (HazardKind::Radiation, radiation),
+        ],
+        Biome::Flooded => [
+            (HazardKind::GasPocket, 1),
+            (HazardKind::CaveIn, 2),
+            (HazardKind::Pit, 4),
+            (HazardKind::Radiation, radiation),
+        ],
+        Biome::Volcanic => [
+            (HazardKind::GasPocket, 4),
+            (HazardKind::CaveIn, 3),
+            (HazardKind::Pit, 1),
+            (HazardKind::Radiation, 2 * radiation),
+        ],
+    }
+}
+
+// Hide hazards around the level, away from the landing spot. Unstable rock
+//   goes in walls and deposits, pits under open floor, and gas and radiation
+//   spread over a patch of floor around where they're rolled. Landmarks are
+//   never covered, bar deposits mined out from under unstable rock.
+fn place_hazards(params: &EmbarkParams, depth: u32, tilemap: &mut TileMap) {
+    let rng = RandGenerator::new();
+    rng.srand(level_seed(params, depth) ^ HAZARD_SEED_SALT);
+    let count = HAZARD_COUNT + DEPTH_EXTRA_HAZARDS * depth as usize;
+    let weights = hazard_weights(params.biome, depth);
+    let total: u32 = weights.iter().map(|(_, weight)| weight).sum();
+    let spawn = spawn_point(params);
+    let dims = (tilemap.width(), tilemap.height());
+
+    for _ in 0..count {
+        let mut roll = rng.gen_range(0, total);
+        let Some((kind, _)) = weights.iter().find(|(_, weight)| {
+            if roll < *weight {
+                return true;
+            }
+            roll -= weight;
+            false
+        }) else {
+            continue;
+        };
+        // Bounded so a cramped map can't stall generation
+        for _ in 0..HAZARD_PLACEMENT_TRIES {
+            let center = random_interior_pos(&rng, dims);
+            let cells = hazard_cells(tilemap, *kind, center, spawn);
+            if cells.is_empty() {
+                continue;
+            }
+            for pos in cells {
+                tilemap.hazards.insert(pos, Hazard::new(*kind));
+            }
+            break;
+        }
+    }
+}
+
+// Tiles a hazard of `kind` rolled at `center` would cover, if it fits there
+fn hazard_cells(tilemap: &TileMap, kind: HazardKind, center: Pos, spawn: Pos) -> Vec<Pos> {
+    let free = |pos: Pos| {
+        pos.distance_sq(spawn) >= HAZARD_MIN_SPAWN_DISTANCE.pow(2) && tilemap.hazard(pos).is_none()
+    };
+    let open = |pos: Pos| {
+        matches!(tilemap.get(pos), Some(tile) if is_walkable(tile) && !is_landmark(tile))
+            && free(pos)
+    };
+    let radius = match kind {
+        HazardKind::CaveIn => {
+            let unstable = matches!(tilemap.get(center), Some(Tile::Wall | Tile::Resource(_)));
+            return if unstable && free(center) {
+                vec![center]
+            } else {
+                vec![]
+            };
+        }
+        HazardKind::Pit => 0,
+        HazardKind::GasPocket => GAS_RADIUS,
+        HazardKind::Radiation => RADIATION_RADIUS,
+    };
+    if !open(center) {
+        return vec![];
+    }
+    let mut cells = vec![];
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let pos = center + Pos::new(dx, dy);
+            if pos.distance_sq(center) <= radius.pow(2) && open(pos) {
+                cells.push(pos);
+            }
+        }
+    }
+    cells
+}
+
+// Tiles the player has to be able to walk to from the spawn
+fn is_landmark(tile: &Tile) -> bool {
+    matches!(
+        tile,
+        Tile::Resource(_) | Tile::Extraction | Tile::StairsDown | Tile::StairsUp | Tile::Cache(_)
+    )
+}
+
+// Whether the player can cross `tile` without digging or getting hurt
+pub fn is_walkable(tile: &Tile) -> bool {
+    tile.props().passable && tile.props().hazard_damage <= 0.0
+}
+
+// Validation pass run once a level is laid out: flood fill from the spawn,
+//   then tunnel through to any landmark that came out walled off. Tunnels
+//   prefer existing floor, so they only cut through what's in the way.
+//   Returns false if some landmark is sealed off by bedrock.
+fn ensure_connected(tilemap: &mut TileMap, spawn: Pos) -> bool {
+    let walkable = |tilemap: &TileMap, pos| tilemap.get(pos).is_some_and(is_walkable);
+    let mut reached = pathfinding::flood_fill(tilemap, spawn, |pos| walkable(tilemap, pos));
+
+    let landmarks: Vec<Pos> = tilemap
+        .tiles
+        .iter()
+        .filter(|(_, tile)| is_landmark(tile))
+        .map(|(pos, _)| pos)
+        .collect();
+    for landmark in landmarks {
+        if reached.contains(&landmark) {
+            continue;
+        }
+        let tunnel =
+            pathfinding::find_path(tilemap, spawn, landmark, |pos| match tilemap.get(pos)? {
+                Tile::Bedrock => None,
+                tile if is_walkable(tile) => Some(1),
+                _ => Some(TUNNEL_COST),
+            });
+        let Some(tunnel) = tunnel else {
+            warn!("No way to connect {:?} to the spawn", landmark);
+            return false;
+        };
+
+        let mut carved = 0;
+        for pos in tunnel {
+            if !walkable(tilemap, pos) {
+                tilemap.set(pos, Tile::Empty);
+                carved += 1;
+            }
+        }
+        info!("Carved {} tiles to reach {:?}", carved, landmark);
+        reached = pathfinding::flood_fill(tilemap, spawn, |pos| walkable(tilemap, pos));
+    }
+    true
+}
+
+// Scatter creatures over open floor, away from the landing spot, on top of
+//   any already `placed` by prefabs. Deeper levels hold more of them, and a
+//   larger share of the dangerous kinds.
+pub fn generate_creatures(
+    params: &EmbarkParams,
+    depth: u32,
+    tilemap: &TileMap,
+    placed: Vec<Creature>,
+) -> Vec<Creature> {
+    let rng = RandGenerator::new();
+    rng.srand(level_seed(params, depth) ^ CREATURE_SEED_SALT);
+    let count = placed.len() + CREATURE_COUNT + DEPTH_EXTRA_CREATURES * depth as usize;
+    let hound_odds = (1 + depth).min(3);
+
+    let spawn = spawn_point(params);
+    let mut creatures = placed;
+
+    // Bounded so a cramped map can't stall generation
+    for _ in 0..count * 100 {
+        if creatures.len() == count {
+            break;
+        }
+        let pos = random_interior_pos(&rng, (tilemap.width(), tilemap.height()));
+        let open = matches!(tilemap.get(pos), Some(Tile::Empty));
+        let far = pos.distance_sq(spawn) >= CREATURE_MIN_SPAWN_DISTANCE.pow(2);
+        let free = !creatures.iter().any(|c| c.pos == pos);
+        if open && far && free {
+            let kind = if rng.gen_range(0, 4) < hound_odds {
+                CreatureKind::RockHound
+            } else {
+                CreatureKind::CaveCrawler
+            };
+            creatures.push(Creature::new(kind, pos));
+        }
+    }
+
+    creatures
+}
+
+// Where the player lands on a fresh embark
+pub fn spawn_point(params: &EmbarkParams) -> Pos {
+    let (width, height) = params.dims;
+    Pos::new((width / 2) as i32, (height / 2) as i32)
+}
+
+// Any tile inside the bedrock border
+fn random_interior_pos(rng: &RandGenerator, (width, height): (usize, usize)) -> Pos {
+    let x = rng.gen_range(1, width as i32 - 1);
+    let y = rng.gen_range(1, height as i32 - 1);
+    Pos::new(x, y)
+}
+
+fn roll_deposit(rng: &RandGenerator, depth: u32) -> Tile {
+    let kind = &DEPOSIT_KINDS[rng.gen_range(0, DEPOSIT_KINDS.len())];
+    deposit(rng, kind, depth)
+}
+
+// A full deposit of the resource `key`, for showing off in the editor
+pub fn preview_deposit(key: &str) -> Option<Tile> {
+    let kind = DEPOSIT_KINDS.iter().find(|kind| kind.key == key)?;
+    Some(Tile::Resource(TileResource::new(
+        kind.key,
+        kind.color,
+        kind.max_amount,
+        kind.hardness,
+    )))
+}
+
+fn deposit(rng: &RandGenerator, kind: &DepositKind, depth: u32) -> Tile {
+    let amount = (rng.gen_range(kind.min_amount, kind.max_amount) * depth_scale(depth)).round();
+    Tile::Resource(TileResource::new(
+        kind.key,
+        kind.color,
+        amount,
+        kind.hardness,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every landmark has to be walkable from the spawn, whatever the seed,
+    //   with prefabs stamped over the terrain as they are in play
+    #[test]
+    fn landmarks_reachable_from_spawn() {
+        let prefabs = crate::prefab::builtin();
+        for biome in Biome::ALL {
+            for seed in 0..20 {
+                let params = EmbarkParams {
+                    seed,
+                    biome,
+                    ..Default::default()
+                };
+                for depth in 0..params.max_depth {
+                    let level = generate_level(&params, depth, &prefabs);
+                    let tilemap = &level.tilemap;
+                    let spawn = spawn_point(&params);
+                    assert!(
+                        tilemap.get(spawn).is_some_and(is_walkable),
+                        "spawn blocked for seed {} {:?} depth {}",
+                        seed,
+                        biome,
+                        depth
+                    );
+
+                    let reached = pathfinding::flood_fill(tilemap, spawn, |pos| {
+                        tilemap.get(pos).is_some_and(is_walkable)
+                    });
+                    for (pos, tile) in tilemap.tiles.iter() {
+                        assert!(
+                            !is_landmark(tile) || reached.contains(&pos),
+                            "{:?} at {:?} unreachable for seed {} {:?} depth {}",
+                            tile,
+                            pos,
+                            seed,
+                            biome,
+                            depth
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Pads and stairs are rolled on top of each other's tiles, so each one
+    //   has to survive the others being placed
+    #[test]
+    fn pads_and_stairs_all_placed() {
+        for biome in Biome::ALL {
+            for seed in 0..40 {
+                let params = EmbarkParams {
+                    seed,
+                    biome,
+                    ..Default::default()
+                };
+                for depth in 0..params.max_depth {
+                    let tilemap = generate_tilemap(&params, depth);
+                    let count = |wanted: fn(&Tile) -> bool| {
+                        tilemap
+                            .tiles
+                            .iter()
+                            .filter(|(_, tile)| wanted(tile))
+                            .count()
+                    };
+                    let pads = if depth == 0 { EXTRACTION_POINTS } else { 0 };
+                    let stairs = usize::from(depth + 1 < params.max_depth);
+                    assert_eq!(
+                        count(|tile| matches!(tile, Tile::Extraction)),
+                        pads,
+                        "seed {} {:?}",
+                        seed,
+                        biome
+                    );
+                    assert_eq!(
+                        count(|tile| matches!(tile, Tile::StairsDown)),
+                        stairs,
+                        "seed {} {:?}",
+                        seed,
+                        biome
+                    );
+                }
+            }
+        }
+    }
+
+    fn painted_map(layout: &str) -> Prefab {
+        Prefab::parse(&format!("name: Test map\n---\n{}", layout)).unwrap()
+    }
+
+    #[test]
+    fn fixed_map_needs_spawn_and_pad() {
+        assert!(check_fixed_map(&painted_map("@.  E")).is_ok());
+        assert!(check_fixed_map(&painted_map("@..")).is_err());
+        assert!(check_fixed_map(&painted_map("..E")).is_err());
+    }
+
+    // Unpainted cells turn into rock, so the pad has to be tunneled to, and
+    //   a map without a spawn lands on its first open tile
+    #[test]
+    fn fixed_level_connects_and_finds_a_spawn() {
+        let params = EmbarkParams::default();
+        let (level, spawn) = fixed_level(&params, &painted_map(" @  E")).unwrap();
+        assert_eq!(spawn, Pos::new(1, 0));
+        let tilemap = &level.tilemap;
+        let reached = pathfinding::flood_fill(tilemap, spawn, |pos| {
+            tilemap.get(pos).is_some_and(is_walkable)
+        });
+        assert!(reached.contains(&Pos::new(4, 0)));
+
+        let (_, spawn) = fixed_level(&params, &painted_map("  .E")).unwrap();
+        assert_eq!(spawn, Pos::new(2, 0));
+        assert!(fixed_level(&params, &painted_map("%%%")).is_err());
+    }
+}
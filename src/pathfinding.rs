@@ -0,0 +1,130 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use crate::game::TileMap;
+use crate::grid::Pos;
+
+// A* search over the tilemap from `start` to `goal`, moving orthogonally.
+//   `step_cost` gives the cost of entering a tile, or `None` if the tile
+//   can't be entered at all. The returned path excludes `start` and ends at
+//   `goal`; `None` means the goal is unreachable.
+pub fn find_path<C>(tilemap: &TileMap, start: Pos, goal: Pos, step_cost: C) -> Option<Vec<Pos>>
+where
+    C: Fn(Pos) -> Option<u32>,
+{
+    if start == goal {
+        return Some(vec![]);
+    }
+    tilemap.get(goal)?;
+
+    let heuristic = |pos: Pos| pos.manhattan(goal) as u32;
+
+    let mut open = BinaryHeap::new();
+    let mut best_cost: HashMap<Pos, u32> = HashMap::new();
+    let mut came_from: HashMap<Pos, Pos> = HashMap::new();
+    open.push(Reverse((heuristic(start), 0, start)));
+    best_cost.insert(start, 0);
+
+    while let Some(Reverse((_, cost, pos))) = open.pop() {
+        if pos == goal {
+            let mut path = vec![goal];
+            let mut cur = goal;
+            while let Some(prev) = came_from.get(&cur) {
+                if *prev == start {
+                    break;
+                }
+                path.push(*prev);
+                cur = *prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        // Stale entry, a cheaper route here was already expanded
+        if cost > best_cost[&pos] {
+            continue;
+        }
+
+        for next in tilemap.tiles.neighbors(pos) {
+            let Some(step) = step_cost(next) else {
+                continue;
+            };
+            let next_cost = cost + step;
+            if best_cost.get(&next).is_none_or(|best| next_cost < *best) {
+                best_cost.insert(next, next_cost);
+                came_from.insert(next, pos);
+                open.push(Reverse((next_cost + heuristic(next), next_cost, next)));
+            }
+        }
+    }
+
+    None
+}
+
+// Dijkstra search outward from `start` for the cheapest tile to reach that
+//   satisfies `is_goal`. `step_cost` works as in `find_path`, as does the
+//   returned path.
+pub fn path_to_nearest<C, G>(
+    tilemap: &TileMap,
+    start: Pos,
+    step_cost: C,
+    is_goal: G,
+) -> Option<Vec<Pos>>
+where
+    C: Fn(Pos) -> Option<u32>,
+    G: Fn(Pos) -> bool,
+{
+    let mut open = BinaryHeap::from([Reverse((0, start))]);
+    let mut best_cost: HashMap<Pos, u32> = HashMap::from([(start, 0)]);
+    let mut came_from: HashMap<Pos, Pos> = HashMap::new();
+
+    while let Some(Reverse((cost, pos))) = open.pop() {
+        // Stale entry, a cheaper route here was already expanded
+        if cost > best_cost[&pos] {
+            continue;
+        }
+        if is_goal(pos) {
+            let mut path = vec![];
+            let mut cur = pos;
+            while cur != start {
+                path.push(cur);
+                cur = came_from[&cur];
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for next in tilemap.tiles.neighbors(pos) {
+            let Some(step) = step_cost(next) else {
+                continue;
+            };
+            let next_cost = cost + step;
+            if best_cost.get(&next).is_none_or(|best| next_cost < *best) {
+                best_cost.insert(next, next_cost);
+                came_from.insert(next, pos);
+                open.push(Reverse((next_cost, next)));
+            }
+        }
+    }
+
+    None
+}
+
+// Every tile reachable from `start` without crossing anything that isn't
+//   `passable`, `start` included
+pub fn flood_fill<P>(tilemap: &TileMap, start: Pos, passable: P) -> HashSet<Pos>
+where
+    P: Fn(Pos) -> bool,
+{
+    let mut reached = HashSet::from([start]);
+    let mut frontier = VecDeque::from([start]);
+
+    while let Some(pos) = frontier.pop_front() {
+        for next in tilemap.tiles.neighbors(pos) {
+            if !reached.contains(&next) && passable(next) {
+                reached.insert(next);
+                frontier.push_back(next);
+            }
+        }
+    }
+
+    reached
+}
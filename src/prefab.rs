@@ -0,0 +1,319 @@
+// Handcrafted set pieces stamped into generated levels
+//   Prefabs are plain text: a few `key: value` header lines, a `---`
+//   separator, then the layout itself. Layout characters are looked up in
+//   the legend, which starts out as `DEFAULT_LEGEND` and can be extended
+//   per file with `legend: <char> <name>` lines. Spaces leave whatever the
+//   generator rolled underneath untouched.
+//
+//   name: Flooded shrine
+//   frequency: caverns 1, flooded 4
+//   legend: $ iron_ore
+//   ---
+//    ~~~
+//   ~~$~~
+//    ~c~
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use macroquad::prelude::*;
+
+use crate::creature::CreatureKind;
+use crate::game::Biome;
+use crate::game::Tile;
+use crate::grid::Grid;
+
+// Where prefabs beyond the built in ones are read from and saved to
+pub const PREFAB_DIR: &str = "prefabs";
+
+const BUILTIN_PREFABS: [(&str, &str); 4] = [
+    (
+        "flooded_shrine",
+        include_str!("../assets/prefabs/flooded_shrine.txt"),
+    ),
+    ("hound_den", include_str!("../assets/prefabs/hound_den.txt")),
+    (
+        "magma_vault",
+        include_str!("../assets/prefabs/magma_vault.txt"),
+    ),
+    (
+        "collapsed_shaft",
+        include_str!("../assets/prefabs/collapsed_shaft.txt"),
+    ),
+];
+
+// Characters every prefab understands without declaring them
+pub const DEFAULT_LEGEND: [(char, &str); 17] = [
+    ('.', "empty"),
+    ('#', "wall"),
+    ('%', "bedrock"),
+    ('~', "water"),
+    ('^', "lava"),
+    ('+', "door"),
+    (',', "rubble"),
+    ('E', "extraction"),
+    ('>', "stairs_down"),
+    ('<', "stairs_up"),
+    ('&', "cache"),
+    ('i', "iron_ore"),
+    ('b', "bauxite"),
+    ('s', "sandstone"),
+    ('c', "cave_crawler"),
+    ('h', "rock_hound"),
+    ('@', "spawn"),
+];
+
+// What one character of a layout turns into
+#[derive(Clone, Debug)]
+pub enum PrefabCell {
+    Tile(Tile),
+    // A deposit of the named resource, rolled like any other deposit
+    Deposit(String),
+    // A creature standing on open floor
+    Creature(CreatureKind),
+    // Where the player starts on a fixed map; plain floor in a prefab
+    Spawn,
+}
+
+impl PrefabCell {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let cell = match name {
+            "empty" => PrefabCell::Tile(Tile::Empty),
+            "wall" => PrefabCell::Tile(Tile::Wall),
+            "bedrock" => PrefabCell::Tile(Tile::Bedrock),
+            "water" => PrefabCell::Tile(Tile::Water),
+            "lava" => PrefabCell::Tile(Tile::Lava),
+            "door" => PrefabCell::Tile(Tile::Door),
+            "rubble" => PrefabCell::Tile(Tile::Rubble),
+            "extraction" => PrefabCell::Tile(Tile::Extraction),
+            "stairs_down" => PrefabCell::Tile(Tile::StairsDown),
+            "stairs_up" => PrefabCell::Tile(Tile::StairsUp),
+            // Filled with loot when stamped
+            "cache" => PrefabCell::Tile(Tile::Cache(vec![])),
+            "iron_ore" | "bauxite" | "sandstone" => PrefabCell::Deposit(name.to_string()),
+            "cave_crawler" => PrefabCell::Creature(CreatureKind::CaveCrawler),
+            "rock_hound" => PrefabCell::Creature(CreatureKind::RockHound),
+            "spawn" => PrefabCell::Spawn,
+            _ => return None,
+        };
+        Some(cell)
+    }
+
+    // Inverse of `from_name`
+    pub fn name(&self) -> &str {
+        match self {
+            PrefabCell::Tile(tile) => tile_name(tile),
+            PrefabCell::Deposit(key) => key,
+            PrefabCell::Creature(kind) => creature_name(*kind),
+            PrefabCell::Spawn => "spawn",
+        }
+    }
+
+    pub fn glyph(&self) -> char {
+        glyph(self.name()).expect("every cell has a default glyph")
+    }
+}
+
+// Legend names for tiles and creatures, as understood by `PrefabCell::from_name`
+pub fn tile_name(tile: &Tile) -> &str {
+    match tile {
+        Tile::Empty => "empty",
+        Tile::Wall => "wall",
+        Tile::Bedrock => "bedrock",
+        Tile::Water => "water",
+        Tile::Lava => "lava",
+        Tile::Door => "door",
+        Tile::Rubble => "rubble",
+        Tile::Resource(resource) => &resource.key,
+        Tile::Extraction => "extraction",
+        Tile::StairsDown => "stairs_down",
+        Tile::StairsUp => "stairs_up",
+        Tile::Cache(_) => "cache",
+    }
+}
+
+pub fn creature_name(kind: CreatureKind) -> &'static str {
+    match kind {
+        CreatureKind::CaveCrawler => "cave_crawler",
+        CreatureKind::RockHound => "rock_hound",
+    }
+}
+
+// Character standing for `name` in the default legend
+pub fn glyph(name: &str) -> Option<char> {
+    DEFAULT_LEGEND
+        .iter()
+        .find(|(_, legend_name)| *legend_name == name)
+        .map(|(glyph, _)| *glyph)
+}
+
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    pub name: String,
+    // Relative odds of being picked for a level, per biome. Biomes that
+    //   aren't listed never see the prefab.
+    pub frequency: HashMap<Biome, u32>,
+    // `None` cells leave the generated terrain alone
+    pub cells: Grid<Option<PrefabCell>>,
+}
+
+impl Prefab {
+    pub fn frequency(&self, biome: Biome) -> u32 {
+        self.frequency.get(&biome).copied().unwrap_or(0)
+    }
+
+    // Write the prefab back out in the format `parse` reads, using only the
+    //   default legend
+    pub fn to_text(&self) -> String {
+        let mut text = format!("name: {}\n", self.name);
+        let frequency: Vec<String> = Biome::ALL
+            .iter()
+            .filter(|biome| self.frequency(**biome) > 0)
+            .map(|biome| format!("{} {}", biome.name(), self.frequency(*biome)))
+            .collect();
+        if !frequency.is_empty() {
+            text += &format!("frequency: {}\n", frequency.join(", "));
+        }
+        text += "---\n";
+        for row in self.cells.rows() {
+            let line: String = row
+                .iter()
+                .map(|cell| cell.as_ref().map_or(' ', PrefabCell::glyph))
+                .collect();
+            text += line.trim_end();
+            text += "\n";
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Prefab, String> {
+        let mut lines = text.lines();
+        let mut name = None;
+        let mut frequency = HashMap::new();
+        let mut legend: HashMap<char, PrefabCell> = DEFAULT_LEGEND
+            .iter()
+            .filter_map(|(glyph, name)| Some((*glyph, PrefabCell::from_name(name)?)))
+            .collect();
+
+        for line in lines.by_ref() {
+            let line = line.trim();
+            if line == "---" {
+                break;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("Expected `key: value`, found `{}`", line))?;
+            let value = value.trim();
+            match key.trim() {
+                "name" => name = Some(value.to_string()),
+                "frequency" => {
+                    for entry in value.split(',') {
+                        let (biome, weight) = entry
+                            .trim()
+                            .split_once(' ')
+                            .ok_or_else(|| format!("Expected `biome weight`, found `{}`", entry))?;
+                        let biome = Biome::from_name(biome)
+                            .ok_or_else(|| format!("Unknown biome `{}`", biome))?;
+                        let weight = weight
+                            .trim()
+                            .parse()
+                            .map_err(|_| format!("Bad frequency `{}`", weight))?;
+                        frequency.insert(biome, weight);
+                    }
+                }
+                "legend" => {
+                    let mut chars = value.chars();
+                    let glyph = chars.next().ok_or("Empty legend entry")?;
+                    let cell_name = chars.as_str().trim();
+                    let cell = PrefabCell::from_name(cell_name)
+                        .ok_or_else(|| format!("Unknown legend entry `{}`", cell_name))?;
+                    legend.insert(glyph, cell);
+                }
+                other => return Err(format!("Unknown key `{}`", other)),
+            }
+        }
+
+        let rows: Vec<&str> = lines.collect();
+        let height = rows.len();
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        if width == 0 || height == 0 {
+            return Err("Prefab has no layout".to_string());
+        }
+
+        let mut unknown = None;
+        let cells = Grid::from_fn(width, height, |pos| {
+            let glyph = rows[pos.y as usize].chars().nth(pos.x as usize)?;
+            if glyph == ' ' {
+                return None;
+            }
+            let cell = legend.get(&glyph).cloned();
+            if cell.is_none() {
+                unknown = Some(glyph);
+            }
+            cell
+        });
+        if let Some(glyph) = unknown {
+            return Err(format!("Character `{}` isn't in the legend", glyph));
+        }
+
+        Ok(Prefab {
+            name: name.ok_or("Prefab has no name")?,
+            frequency,
+            cells,
+        })
+    }
+}
+
+// The prefabs compiled into the game, the same wherever it's run from
+pub fn builtin() -> Vec<Prefab> {
+    let mut prefabs = vec![];
+    for (file, text) in BUILTIN_PREFABS {
+        match Prefab::parse(text) {
+            Ok(prefab) => prefabs.push(prefab),
+            Err(err) => warn!("Built in prefab {} is broken: {}", file, err),
+        }
+    }
+    prefabs
+}
+
+// The built in prefabs, plus any found in `PREFAB_DIR`. Prefabs that fail
+//   to parse are skipped with a warning rather than stopping the game.
+//   `PREFAB_DIR` is relative to the working directory, so a seed only
+//   generates the same levels when run from the same place; anything that
+//   needs levels to be reproducible should use `builtin` instead.
+pub fn load_all() -> Vec<Prefab> {
+    let mut prefabs = builtin();
+
+    let Ok(entries) = fs::read_dir(PREFAB_DIR) else {
+        return prefabs;
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    // Directory order isn't stable, and generation has to be
+    paths.sort();
+    for path in paths {
+        match load_file(&path) {
+            Ok(prefab) => {
+                info!("Loaded prefab {} from {}", prefab.name, path.display());
+                prefabs.push(prefab);
+            }
+            Err(err) => warn!("Skipping prefab {}: {}", path.display(), err),
+        }
+    }
+    prefabs
+}
+
+pub fn load_file(path: &Path) -> Result<Prefab, String> {
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    Prefab::parse(&text)
+}
@@ -3,12 +3,33 @@ use macroquad::ui::{
     hash, root_ui,
     widgets::{self, Group},
 };
+use std::ops::Range;
+use std::path::Path;
 
+use crate::creature::Creature;
+use crate::editor::SaveTarget;
+use crate::editor::MAP_DIR;
+use crate::game::Biome;
 use crate::game::GameScreen;
 use crate::game::GameState;
 use crate::game::Location;
 use crate::game::Tile;
+use crate::game::TileMap;
 use crate::game::UiEvent;
+use crate::game::Visibility;
+use crate::game::ZOOM_LEVELS;
+use crate::grid::Pos;
+use crate::item::Slot;
+use crate::item::RECIPES;
+use crate::prefab::DEFAULT_LEGEND;
+use crate::turn;
+
+// Tiles the player has never seen
+const FOG_COLOR: Color = Color::new(0.15, 0.15, 0.15, 1.0);
+// Laid over tiles that are remembered but not currently in view
+const REMEMBERED_SHADE: Color = Color::new(0.0, 0.0, 0.0, 0.5);
+// Smallest on-screen tile size, in pixels, before any zoom is applied
+const MIN_TILE_SIZE: f32 = 2.0;
 
 fn draw_idle_screen(state: &GameState) -> Option<UiEvent> {
     let mut return_event = None;
@@ -47,6 +68,64 @@ fn draw_idle_screen(state: &GameState) -> Option<UiEvent> {
             });
         });
         ui.separator();
+        ui.tree_node(hash!(), "Workshop", |ui| {
+            for (index, recipe) in RECIPES.iter().enumerate() {
+                let cost: Vec<String> = recipe
+                    .cost
+                    .iter()
+                    .map(|(key, amount)| format!("{} {}", amount, key))
+                    .collect();
+                ui.label(
+                    None,
+                    &format!(
+                        "{} [{}]: {}",
+                        recipe.name,
+                        recipe.slot.name(),
+                        recipe.stats.describe()
+                    ),
+                );
+                ui.label(None, &format!("  {}", cost.join(", ")));
+                if state.can_afford(recipe.cost) {
+                    ui.same_line(0.0);
+                    if ui.button(None, format!("Build {}", recipe.name)) {
+                        return_event = Some(UiEvent::CraftItem(index));
+                    }
+                }
+            }
+        });
+        ui.separator();
+        ui.tree_node(hash!(), "Equipment", |ui| {
+            for slot in Slot::ALL {
+                let equipped = match state.equipment.get(&slot) {
+                    Some(item) => item.describe(),
+                    None => "(empty)".to_string(),
+                };
+                ui.label(None, &format!("{}: {}", slot.name(), equipped));
+            }
+            ui.separator();
+            for (index, item) in state.inventory.iter().enumerate() {
+                if ui.button(None, format!("Equip {}", item.describe())) {
+                    return_event = Some(UiEvent::EquipItem(index));
+                }
+            }
+        });
+        ui.separator();
+        ui.tree_node(hash!(), "Developer", |ui| {
+            if ui.button(None, "Map Editor") {
+                return_event = Some(UiEvent::StateTransition(GameScreen::Editor));
+            }
+            let maps = state
+                .editor_state
+                .saved_files
+                .iter()
+                .filter(|path| path.parent() == Some(Path::new(MAP_DIR)));
+            for path in maps {
+                if ui.button(None, format!("Play {}", path.display())) {
+                    return_event = Some(UiEvent::EmbarkMap(path.clone()));
+                }
+            }
+        });
+        ui.separator();
         ui.tree_node(hash!(), "Placeholder Functions", |ui| {
             if ui.button(None, "Empty button") {
                 info!("Pressed empty button!");
@@ -64,6 +143,15 @@ fn draw_idle_screen(state: &GameState) -> Option<UiEvent> {
                             return_event = Some(UiEvent::EmbarkLocation(*location));
                             // Copies if necessary
                         }
+                        let visited = if state.sites.contains_key(&val.seed) {
+                            " (visited)"
+                        } else {
+                            ""
+                        };
+                        ui.same_line(0.0);
+                        if ui.button(None, format!("Abandon {:x}{}", val.seed, visited)) {
+                            return_event = Some(UiEvent::AbandonLocation(*location));
+                        }
                     }
                 }
             }
@@ -99,11 +187,187 @@ fn draw_idle_screen(state: &GameState) -> Option<UiEvent> {
     return_event
 }
 
-fn draw_embark_screen(state: &GameState) {
+// Maps tilemap coordinates onto the embark panel, given the camera position
+//   and zoom level. Anything drawn through it is clipped to the panel.
+struct Viewport {
+    area: Rect,
+    // Screen position of the upper left corner of tile (0, 0)
+    origin: Vec2,
+    tile_size: f32,
+}
+
+impl Viewport {
+    fn centered_on(state: &GameState, tilemap: &TileMap, area: Rect, focus: Pos) -> Self {
+        // Largest tile size that still fits the whole map, scaled by the zoom level
+        let map_tiles = vec2(tilemap.width() as f32, tilemap.height() as f32);
+        let fit = (area.w / map_tiles.x)
+            .min(area.h / map_tiles.y)
+            .max(MIN_TILE_SIZE);
+        let tile_size = fit * ZOOM_LEVELS[state.embark_state.zoom_level];
+
+        let focus = vec2(focus.x as f32 + 0.5, focus.y as f32 + 0.5);
+        let map_size = map_tiles * tile_size;
+        let origin = vec2(
+            Self::clamp_axis(area.x, area.w, map_size.x, focus.x * tile_size),
+            Self::clamp_axis(area.y, area.h, map_size.y, focus.y * tile_size),
+        );
+
+        Viewport {
+            area,
+            origin,
+            tile_size,
+        }
+    }
+
+    // Center the camera on `target` along one axis, without scrolling past
+    //   the map edges. Maps smaller than the panel are simply centered.
+    fn clamp_axis(start: f32, extent: f32, map_extent: f32, target: f32) -> f32 {
+        if map_extent <= extent {
+            return start + (extent - map_extent) / 2.0;
+        }
+        let origin = start + extent / 2.0 - target;
+        origin.clamp(start + extent - map_extent, start)
+    }
+
+    fn tile_origin(&self, pos: Pos) -> (f32, f32) {
+        (
+            self.origin.x + pos.x as f32 * self.tile_size,
+            self.origin.y + pos.y as f32 * self.tile_size,
+        )
+    }
+
+    // Column and row ranges of the tiles at least partially on screen
+    fn visible_tiles(&self, tilemap: &TileMap) -> (Range<i32>, Range<i32>) {
+        let first = (self.area.point() - self.origin) / self.tile_size;
+        let last = (self.area.point() + self.area.size() - self.origin) / self.tile_size;
+        let cols =
+            (first.x.floor().max(0.0) as i32)..(last.x.ceil().min(tilemap.width() as f32) as i32);
+        let rows =
+            (first.y.floor().max(0.0) as i32)..(last.y.ceil().min(tilemap.height() as f32) as i32);
+        (cols, rows)
+    }
+
+    // Tile under a screen position, if it's on the map and inside the panel
+    fn tile_at(&self, tilemap: &TileMap, screen: Vec2) -> Option<Pos> {
+        if !self.area.contains(screen) {
+            return None;
+        }
+        let tile = ((screen - self.origin) / self.tile_size).floor();
+        let pos = Pos::new(tile.x as i32, tile.y as i32);
+        tilemap.tiles.in_bounds(pos).then_some(pos)
+    }
+
+    fn fill(&self, x: f32, y: f32, w: f32, h: f32, color: Color) {
+        if let Some(clipped) = Rect::new(x, y, w, h).intersect(self.area) {
+            draw_rectangle(clipped.x, clipped.y, clipped.w, clipped.h, color);
+        }
+    }
+}
+
+// Color a tile is drawn in. Deposits fade towards the floor color as
+//   they're mined out.
+pub fn tile_color(tile: &Tile) -> Color {
+    let base = tile.props().color;
+    let Tile::Resource(resource) = tile else {
+        return base;
+    };
+    let alpha = 0.25 + 0.75 * resource.richness();
+    Color::new(
+        base.r + (resource.color.r - base.r) * alpha,
+        base.g + (resource.color.g - base.g) * alpha,
+        base.b + (resource.color.b - base.b) * alpha,
+        1.0,
+    )
+}
+
+// Draw as much of `tilemap` and the creatures on it as the player knows
+//   about. Shared by the embark screen and the editor.
+fn draw_tilemap(viewport: &Viewport, tilemap: &TileMap, creatures: &[Creature]) {
+    let tile_size = viewport.tile_size;
+    let (cols, rows) = viewport.visible_tiles(tilemap);
+
+    for r in rows {
+        for c in cols.clone() {
+            let pos = Pos::new(c, r);
+            let (tile_x, tile_y) = viewport.tile_origin(pos);
+            let Some(tile) = tilemap.get(pos) else {
+                continue;
+            };
+            let visibility = tilemap.visibility(pos);
+            if visibility == Visibility::Unseen {
+                viewport.fill(tile_x, tile_y, tile_size, tile_size, FOG_COLOR);
+                continue;
+            }
+
+            viewport.fill(tile_x, tile_y, tile_size, tile_size, tile_color(tile));
+
+            if let Tile::Resource(tileresource) = tile {
+                // Mining progress towards the next yield, as a bar along the bottom
+                let progress = tileresource.progress_fraction();
+                if progress > 0.0 {
+                    let bar_height = tile_size / 4.0;
+                    viewport.fill(
+                        tile_x,
+                        tile_y + tile_size - bar_height,
+                        tile_size * progress,
+                        bar_height,
+                        YELLOW,
+                    );
+                }
+            }
+
+            // Digging progress into rock, as a bar along the bottom
+            let dug = tilemap.dig_fraction(pos);
+            if dug > 0.0 {
+                let bar_height = tile_size / 4.0;
+                viewport.fill(
+                    tile_x,
+                    tile_y + tile_size - bar_height,
+                    tile_size * dug,
+                    bar_height,
+                    ORANGE,
+                );
+            }
+
+            // Known hazards, as a frame around the tile
+            if let Some(hazard) = tilemap.hazard(pos).filter(|hazard| hazard.discovered) {
+                let color = hazard.kind.stats().color;
+                let edge = (tile_size / 8.0).max(1.0);
+                viewport.fill(tile_x, tile_y, tile_size, edge, color);
+                viewport.fill(tile_x, tile_y + tile_size - edge, tile_size, edge, color);
+                viewport.fill(tile_x, tile_y, edge, tile_size, color);
+                viewport.fill(tile_x + tile_size - edge, tile_y, edge, tile_size, color);
+            }
+
+            if visibility == Visibility::Remembered {
+                viewport.fill(tile_x, tile_y, tile_size, tile_size, REMEMBERED_SHADE);
+            }
+        }
+    }
+
+    for creature in creatures {
+        if tilemap.visibility(creature.pos) != Visibility::Visible {
+            continue;
+        }
+        let (creature_x, creature_y) = viewport.tile_origin(creature.pos);
+        let inset = tile_size / 6.0;
+        viewport.fill(
+            creature_x + inset,
+            creature_y + inset,
+            tile_size - 2.0 * inset,
+            tile_size - 2.0 * inset,
+            creature.kind.stats().color,
+        );
+    }
+}
+
+fn draw_embark_screen(state: &GameState) -> Option<UiEvent> {
+    let mut return_event = None;
+
     // Draw RL screen
     let gutter = 10.0;
-    let main_width = 1.0;
-    let _res_width = 0.2;
+    let main_width = 0.8;
+    let res_width = 0.2;
     let height = 0.9;
 
     draw_rectangle(
@@ -122,41 +386,270 @@ fn draw_embark_screen(state: &GameState) {
         todo!()
     }
 
-    let tile_width = 7.5f32;
-    let tile_height = 7.5f32;
-    let center_x = state.screen_width / 2.0;
-    let center_y = height * state.screen_height / 2.0;
-    let upper_left_x = center_x - tile_width * local_tilemap.width / 2.0;
-    let upper_left_y = center_y - tile_height * local_tilemap.height / 2.0;
-
-    for r in 0..(local_tilemap.height as u32) {
-        let tile_y = upper_left_y + (r as f32) * tile_height;
-        for c in 0..(local_tilemap.width as u32) {
-            let tile_x = upper_left_x + (c as f32) * tile_width;
-
-            let tile_index: usize = (r * (local_tilemap.width as u32) + c).try_into().unwrap();
-            match &local_tilemap.tiles[tile_index] {
-                Tile::Wall => {
-                    draw_rectangle(tile_x, tile_y, tile_width, tile_height, BLACK);
-                }
-                Tile::Empty => {
-                    draw_rectangle(tile_x, tile_y, tile_width, tile_height, WHITE);
-                }
-                Tile::Resource(tileresource) => {
-                    draw_rectangle(tile_x, tile_y, tile_width, tile_height, tileresource.color);
+    let area = Rect::new(
+        gutter,
+        gutter,
+        main_width * state.screen_width - 2.0 * gutter,
+        height * state.screen_height - 2.0 * gutter,
+    );
+    let viewport = Viewport::centered_on(state, local_tilemap, area, state.embark_state.player);
+    let tile_size = viewport.tile_size;
+    draw_tilemap(&viewport, local_tilemap, &state.embark_state.creatures);
+
+    // Planned travel route
+    for &step in &state.embark_state.travel_path {
+        let (step_x, step_y) = viewport.tile_origin(step);
+        let inset = tile_size * 0.4;
+        viewport.fill(
+            step_x + inset,
+            step_y + inset,
+            tile_size - 2.0 * inset,
+            tile_size - 2.0 * inset,
+            RED,
+        );
+    }
+
+    if is_mouse_button_pressed(MouseButton::Left) {
+        if let Some(pos) = viewport.tile_at(local_tilemap, mouse_position().into()) {
+            return_event = Some(UiEvent::TileClicked(pos));
+        }
+    }
+
+    let (player_x, player_y) = viewport.tile_origin(state.embark_state.player);
+    let r = tile_size / 2.0;
+    // In pixels; account for (x,y) referring to center in draw_circle, not upper left
+    draw_circle(player_x + r, player_y + r, r, RED);
+
+    // Embark info window
+    draw_rectangle(
+        main_width * state.screen_width + gutter,
+        gutter,
+        res_width * state.screen_width - 2.0 * gutter,
+        height * state.screen_height - 2.0 * gutter,
+        LIGHTGRAY,
+    );
+    widgets::Window::new(
+        hash!(),
+        vec2(main_width * state.screen_width + gutter, gutter),
+        vec2(
+            res_width * state.screen_width - 2.0 * gutter,
+            height * state.screen_height - 2.0 * gutter,
+        ),
+    )
+    .movable(false)
+    .label("Embark Window")
+    .ui(&mut root_ui(), |ui| {
+        ui.label(None, &format!("Depth: {}", state.embark_state.depth));
+        ui.label(
+            None,
+            &format!(
+                "HP: {:.0} / {:.0}",
+                state.embark_state.hp, state.embark_state.max_hp
+            ),
+        );
+        ui.label(
+            None,
+            &format!("Speed: {} / {}", state.player_speed(), turn::NORMAL_SPEED),
+        );
+        let energy = state.resources.get("energy").unwrap();
+        ui.label(None, &energy.display(&"energy".to_string()));
+        if state.is_low_energy() {
+            ui.label(None, "LOW ENERGY - return to base!");
+        }
+        ui.separator();
+        ui.label(
+            None,
+            &format!(
+                "Cargo: {:.3} / {:.3}",
+                state.cargo_weight(),
+                state.embark_state.cargo_capacity
+            ),
+        );
+        for (key, amount) in &state.embark_state.cargo {
+            ui.label(None, &format!("  {}: {:.3}", key, amount));
+        }
+        if !state.embark_state.found_items.is_empty() {
+            ui.label(None, "Found:");
+            for item in &state.embark_state.found_items {
+                ui.label(None, &format!("  {}", item.name));
+            }
+        }
+        ui.separator();
+        if let Some(tilemap) = &state.embark_state.tilemap {
+            for creature in &state.embark_state.creatures {
+                if tilemap.visibility(creature.pos) == Visibility::Visible {
+                    let stats = creature.kind.stats();
+                    ui.label(
+                        None,
+                        &format!(
+                            "{}: {:.0} / {:.0} HP",
+                            stats.name, creature.hp, stats.max_hp
+                        ),
+                    );
                 }
             }
         }
+        ui.separator();
+        for message in state.messages.iter().rev().take(20) {
+            ui.label(None, message);
+        }
+    });
+
+    return_event
+}
+
+fn draw_editor_screen(state: &GameState) -> Option<UiEvent> {
+    let mut return_event = None;
+    let editor = &state.editor_state;
+
+    let gutter = 10.0;
+    let main_width = 0.8;
+    let res_width = 0.2;
+    let height = 0.9;
+
+    let area = Rect::new(
+        gutter,
+        gutter,
+        main_width * state.screen_width - 2.0 * gutter,
+        height * state.screen_height - 2.0 * gutter,
+    );
+    draw_rectangle(area.x, area.y, area.w, area.h, LIGHTGRAY);
+
+    let viewport = Viewport::centered_on(state, &editor.tilemap, area, editor.focus);
+    draw_tilemap(&viewport, &editor.tilemap, &editor.creatures);
+
+    if let Some(spawn) = editor.spawn {
+        let (spawn_x, spawn_y) = viewport.tile_origin(spawn);
+        let r = viewport.tile_size / 2.0;
+        draw_circle(spawn_x + r, spawn_y + r, r, RED);
     }
 
-    let player_x = state.embark_state.player_x as f32; // In tiles
-    let player_y = state.embark_state.player_y as f32;
+    // Paint for as long as the button is held, so strokes can be dragged out
+    if is_mouse_button_down(MouseButton::Left) {
+        if let Some(pos) = viewport.tile_at(&editor.tilemap, mouse_position().into()) {
+            return_event = Some(UiEvent::TileClicked(pos));
+        }
+    }
 
-    let r = tile_width / 2.0;
-    // In pixels; account for (x,y) referring to center in draw_circle, not upper left
-    let x = upper_left_x + player_x * tile_width + r;
-    let y = upper_left_y + player_y * tile_height + r;
-    draw_circle(x, y, r, RED);
+    // Editor tools window
+    draw_rectangle(
+        main_width * state.screen_width + gutter,
+        gutter,
+        res_width * state.screen_width - 2.0 * gutter,
+        height * state.screen_height - 2.0 * gutter,
+        LIGHTGRAY,
+    );
+    widgets::Window::new(
+        hash!(),
+        vec2(main_width * state.screen_width + gutter, gutter),
+        vec2(
+            res_width * state.screen_width - 2.0 * gutter,
+            height * state.screen_height - 2.0 * gutter,
+        ),
+    )
+    .movable(false)
+    .label("Editor Window")
+    .ui(&mut root_ui(), |ui| {
+        let mut name = editor.prefab.name.clone();
+        ui.input_text(hash!(), "Name", &mut name);
+        if name != editor.prefab.name {
+            return_event = Some(UiEvent::EditorRename(name));
+        }
+        let file = match &editor.path {
+            Some(path) => path.display().to_string(),
+            None => "(unsaved)".to_string(),
+        };
+        ui.label(None, &file);
+        ui.label(
+            None,
+            &format!(
+                "Size: {} x {}",
+                editor.prefab.cells.width(),
+                editor.prefab.cells.height()
+            ),
+        );
+        if ui.button(None, "Wider") {
+            return_event = Some(UiEvent::EditorResize(Pos::new(1, 0)));
+        }
+        ui.same_line(0.0);
+        if ui.button(None, "Narrower") {
+            return_event = Some(UiEvent::EditorResize(Pos::new(-1, 0)));
+        }
+        ui.same_line(0.0);
+        if ui.button(None, "Taller") {
+            return_event = Some(UiEvent::EditorResize(Pos::new(0, 1)));
+        }
+        ui.same_line(0.0);
+        if ui.button(None, "Shorter") {
+            return_event = Some(UiEvent::EditorResize(Pos::new(0, -1)));
+        }
+        ui.separator();
+
+        ui.label(None, "Brush");
+        let erase = if editor.brush.is_none() {
+            "> erase"
+        } else {
+            "erase"
+        };
+        if ui.button(None, erase) {
+            return_event = Some(UiEvent::EditorBrush(None));
+        }
+        for (glyph, name) in DEFAULT_LEGEND {
+            let label = if editor.brush == Some(name) {
+                format!("> {} {}", glyph, name)
+            } else {
+                format!("{} {}", glyph, name)
+            };
+            if ui.button(None, label) {
+                return_event = Some(UiEvent::EditorBrush(Some(name)));
+            }
+        }
+        ui.separator();
+
+        ui.label(None, "Frequency");
+        for biome in Biome::ALL {
+            ui.label(
+                None,
+                &format!("{}: {}", biome.name(), editor.prefab.frequency(biome)),
+            );
+            ui.same_line(0.0);
+            if ui.button(None, format!("+ {}", biome.name())) {
+                return_event = Some(UiEvent::EditorFrequency(biome, 1));
+            }
+            ui.same_line(0.0);
+            if ui.button(None, format!("- {}", biome.name())) {
+                return_event = Some(UiEvent::EditorFrequency(biome, -1));
+            }
+        }
+        ui.separator();
+
+        if ui.button(None, "New") {
+            return_event = Some(UiEvent::EditorNew);
+        }
+        ui.same_line(0.0);
+        if ui.button(None, "Save Prefab") {
+            return_event = Some(UiEvent::EditorSave(SaveTarget::Prefab));
+        }
+        ui.same_line(0.0);
+        if ui.button(None, "Save Map") {
+            return_event = Some(UiEvent::EditorSave(SaveTarget::Map));
+        }
+        for path in &editor.saved_files {
+            if ui.button(None, format!("Load {}", path.display())) {
+                return_event = Some(UiEvent::EditorLoad(path.clone()));
+            }
+        }
+        ui.separator();
+
+        if ui.button(None, "Back to Base") {
+            return_event = Some(UiEvent::StateTransition(GameScreen::Idle));
+        }
+        for message in state.messages.iter().rev().take(10) {
+            ui.label(None, message);
+        }
+    });
+
+    return_event
 }
 
 fn draw_status_bar(state: &GameState) {
@@ -218,8 +711,11 @@ pub fn render_frame(state: &GameState) -> Option<UiEvent> {
         }
         GameScreen::Embark => {
             clear_background(DARKGREEN);
-            draw_embark_screen(state);
-            None
+            draw_embark_screen(state)
+        }
+        GameScreen::Editor => {
+            clear_background(DARKBLUE);
+            draw_editor_screen(state)
         }
     }
 }
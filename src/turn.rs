@@ -0,0 +1,38 @@
+// Turn order on an embark
+//   Every actor, the player included, banks action points at its speed for
+//   each tick of game time, and may act whenever its balance isn't negative.
+//   Actions are paid for in full, so a fast creature gets several moves in
+//   while the player hacks at a wall, and a slow one falls behind.
+
+// Speed of an unburdened player; creature speeds are relative to this
+pub const NORMAL_SPEED: i32 = 10;
+
+// Action points spent per action. Moving is charged once for every point
+//   of the destination tile's `move_cost`.
+const MOVE_COST: i32 = 100;
+const ATTACK_COST: i32 = 100;
+const MINE_COST: i32 = 100;
+const DIG_COST: i32 = 150;
+const WAIT_COST: i32 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Wait,
+    // Carries the `move_cost` of the tile stepped onto
+    Move(u32),
+    Attack,
+    Mine,
+    Dig,
+}
+
+impl Action {
+    pub fn cost(&self) -> i32 {
+        match self {
+            Action::Wait => WAIT_COST,
+            Action::Move(tile_cost) => MOVE_COST * *tile_cost as i32,
+            Action::Attack => ATTACK_COST,
+            Action::Mine => MINE_COST,
+            Action::Dig => DIG_COST,
+        }
+    }
+}